@@ -3,18 +3,48 @@ use crate::ApplicationGDX;
 pub trait AppGDX {
     fn new(gdx: &ApplicationGDX) -> Self;
 
+    /// Called at the rate set with `ApplicationGDXConfig::with_update_rate`, zero or more times
+    /// per frame before `step`, each advancing the simulation by `ApplicationGDX::fixed_delta_time`.
+    #[allow(unused_variables)]
+    fn fixed_step(&mut self, gdx: &mut ApplicationGDX) {}
+
     #[allow(unused_variables)]
     fn step(&mut self, gdx: &mut ApplicationGDX) {}
 
     #[allow(unused_variables)]
     fn resize(&mut self, size: (u32, u32), gdx: &ApplicationGDX) {}
 
+    /// Called when the window loses focus or is minimized, or the app is about to go to the
+    /// background (`AppWillEnterBackground`). `step` and `fixed_step` aren't called until `resume`,
+    /// so game time doesn't advance in between.
     #[allow(unused_variables)]
     fn pause(&mut self, gdx: &ApplicationGDX) {}
 
+    /// Called when the window regains focus or is restored, or the app is back in the foreground
+    /// (`AppDidEnterForeground`).
     #[allow(unused_variables)]
     fn resume(&mut self, gdx: &ApplicationGDX) {}
 
+    /// Called after `Input` opened a newly connected controller with instance id `which`.
+    /// `player` is the player slot it was given, see `Input::player`.
+    #[allow(unused_variables)]
+    fn on_controller_connected(&mut self, which: u32, player: Option<usize>, gdx: &ApplicationGDX) {}
+
+    /// Called after `Input` closed the controller with instance id `which`. `player` is the slot
+    /// it held until now, which is already free again.
+    #[allow(unused_variables)]
+    fn on_controller_disconnected(&mut self, which: u32, player: Option<usize>, gdx: &ApplicationGDX) {}
+
+    /// Called once per frame after `step` with the averaged frame time divided by the target frame
+    /// duration. A `load` above `1.0` means frames take longer than the budget, so this is the
+    /// place to scale quality down. Not called when the frame rate is unlimited.
+    #[allow(unused_variables)]
+    fn on_performance_sample(&mut self, load: f32, gdx: &ApplicationGDX) {}
+
+    /// Called once after the main loop ends, whether the window was closed, `set_should_exit` was
+    /// called or the OS is terminating the app (`AppTerminating`). The display and its GL context
+    /// are still alive here, so textures and buffers can be released explicitly. The app itself is
+    /// dropped right after, before the context is destroyed.
     #[allow(unused_variables)]
-    fn destroy(&mut self, gdx: &ApplicationGDX) {}
+    fn destroy(&mut self, gdx: &mut ApplicationGDX) {}
 }
@@ -0,0 +1,228 @@
+//! Named textures, loaded one by one or in bulk from an `AssetManifest`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::graphics::Graphics;
+use crate::graphics::texture::{TextureLoadError, TextureRegion};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextureOptions {
+    /// Passed on to `Graphics::load_texture`.
+    pub reversed: bool,
+    /// Loads with `Graphics::load_texture_premultiplied` instead.
+    pub premultiplied: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AssetEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub options: TextureOptions,
+}
+
+/// The list of assets for `AssetManager::load_manifest`, usually everything a level or screen
+/// needs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AssetManifest {
+    entries: Vec<AssetEntry>,
+}
+
+impl AssetManifest {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_texture<N: Into<String>, P: Into<PathBuf>>(mut self, name: N, path: P,
+                                                           options: TextureOptions) -> Self {
+        self.entries.push(AssetEntry { name: name.into(), path: path.into(), options });
+        self
+    }
+
+    pub fn entries(&self) -> &[AssetEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Every asset of a manifest that failed to load, with its name and the reason.
+#[derive(Debug)]
+pub struct ManifestError {
+    pub failed: Vec<(String, TextureLoadError)>,
+}
+
+impl fmt::Display for ManifestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} asset(s) failed to load", self.failed.len())?;
+        for (name, error) in &self.failed {
+            write!(f, "\n  {}: {}", name, error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ManifestError {}
+
+#[derive(Default)]
+pub struct AssetManager {
+    textures: HashMap<String, Rc<glium::Texture2d>>,
+}
+
+impl AssetManager {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Loads the texture at `path` under `name`, replacing any texture loaded under that name
+    /// before.
+    pub fn load_texture<N: Into<String>, P: AsRef<Path>>(&mut self, graphics: &Graphics, name: N, path: P,
+                                                         options: TextureOptions) -> Result<(), TextureLoadError> {
+        let texture = if options.premultiplied {
+            graphics.load_texture_premultiplied(path, options.reversed)?
+        } else {
+            graphics.load_texture(path, options.reversed)?
+        };
+        self.textures.insert(name.into(), Rc::new(texture));
+        Ok(())
+    }
+
+    /// Loads every entry of `manifest` in order. A failed entry doesn't stop the others; all
+    /// failures are reported together at the end.
+    ///
+    /// `on_progress(name, loaded, total)` is called after each entry, whether it loaded or not,
+    /// with the entry's name, the number of entries done so far including this one and the
+    /// manifest's length, e.g. to draw a loading bar.
+    pub fn load_manifest<F>(&mut self, graphics: &Graphics, manifest: &AssetManifest,
+                            mut on_progress: F) -> Result<(), ManifestError>
+    where
+        F: FnMut(&str, usize, usize),
+    {
+        let total = manifest.len();
+        let mut failed = Vec::new();
+        for (index, entry) in manifest.entries().iter().enumerate() {
+            if let Err(error) = self.load_texture(graphics, entry.name.as_str(), &entry.path, entry.options) {
+                failed.push((entry.name.clone(), error));
+            }
+            on_progress(&entry.name, index + 1, total);
+        }
+
+        if failed.is_empty() {
+            Ok(())
+        } else {
+            Err(ManifestError { failed })
+        }
+    }
+
+    pub fn insert_texture<N: Into<String>>(&mut self, name: N, texture: Rc<glium::Texture2d>) {
+        self.textures.insert(name.into(), texture);
+    }
+
+    pub fn texture(&self, name: &str) -> Option<&Rc<glium::Texture2d>> {
+        self.textures.get(name)
+    }
+
+    /// The whole texture `name` as a region.
+    pub fn region(&self, name: &str) -> Option<TextureRegion> {
+        self.texture(name).map(|texture| TextureRegion::new(texture.clone()))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.textures.contains_key(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Rc<glium::Texture2d>> {
+        self.textures.remove(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.textures.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::config::ApplicationGDXConfig;
+    use crate::graphics::test_support::hidden_graphics;
+
+    #[test]
+    fn manifest_keeps_entries_in_order() {
+        let premultiplied = TextureOptions { reversed: true, premultiplied: true };
+        let manifest = AssetManifest::new()
+            .with_texture("player", "sprites/player.png", TextureOptions::default())
+            .with_texture("tiles", "sprites/tiles.png", premultiplied);
+
+        assert_eq!(manifest.len(), 2);
+        assert!(!manifest.is_empty());
+        assert!(AssetManifest::new().is_empty());
+        assert_eq!(manifest.entries()[0], AssetEntry {
+            name: "player".to_string(),
+            path: PathBuf::from("sprites/player.png"),
+            options: TextureOptions::default(),
+        });
+        assert_eq!(manifest.entries()[1].name, "tiles");
+        assert_eq!(manifest.entries()[1].options, premultiplied);
+    }
+
+    #[test]
+    #[ignore]
+    fn manager_loads_gets_and_unloads_textures() {
+        let (_sdl_context, graphics) = hidden_graphics(ApplicationGDXConfig::new());
+
+        let manifest = AssetManifest::new()
+            .with_texture("magenta", "assets/magenta_2x3.png", TextureOptions::default())
+            .with_texture("missing", "assets/missing.png", TextureOptions::default())
+            .with_texture("face", "assets/awesomeface.png", TextureOptions { reversed: true, premultiplied: true });
+        let mut assets = AssetManager::new();
+        let mut progress = Vec::new();
+        let error = assets.load_manifest(&graphics, &manifest, |name, loaded, total| {
+            progress.push((name.to_string(), loaded, total));
+        }).unwrap_err();
+
+        assert_eq!(progress, [("magenta".to_string(), 1, 3), ("missing".to_string(), 2, 3), ("face".to_string(), 3, 3)]);
+        assert_eq!(error.failed.len(), 1);
+        assert_eq!(error.failed[0].0, "missing");
+
+        assert_eq!(assets.len(), 2);
+        assert!(assets.contains("face") && !assets.contains("missing"));
+        assert_eq!(assets.texture("magenta").unwrap().dimensions(), (2, 3));
+        assert_eq!(assets.region("magenta").unwrap().size(), glm::vec2(2, 3));
+
+        assert!(assets.remove("magenta").is_some());
+        assert!(assets.texture("magenta").is_none() && assets.region("magenta").is_none());
+        assets.clear();
+        assert!(assets.is_empty());
+    }
+
+    #[test]
+    fn manifest_error_lists_every_failed_asset() {
+        let error = ManifestError {
+            failed: vec![
+                ("player".into(), TextureLoadError::TooLarge { size: (8192, 8192), max_size: 4096 }),
+                ("tiles".into(), TextureLoadError::PixelCountMismatch { expected: 16, actual: 12 }),
+            ],
+        };
+        let message = error.to_string();
+        assert!(message.starts_with("2 asset(s) failed to load"));
+        assert!(message.contains("\n  player: "));
+        assert!(message.contains("\n  tiles: "));
+    }
+}
@@ -1,9 +1,66 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapInterval {
+    Immediate,
+    Vsync,
+    /// Adaptive vsync (late swap tearing). Falls back to `Vsync` if the driver rejects it.
+    Adaptive,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowMode {
+    Windowed,
+    /// Exclusive fullscreen at the configured screen size, changing the display mode.
+    Fullscreen,
+    /// A borderless window covering the whole display at the desktop resolution.
+    BorderlessFullscreen,
+}
+
+/// How a logical size set with `ApplicationGDXConfig::with_logical_size` is mapped onto the
+/// window by `Graphics::logical_projection`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogicalScaling {
+    /// Scales the logical area uniformly to the largest size that fits into the window, keeping
+    /// its aspect ratio. The bars left on two sides are covered by `Graphics::draw_letterbox`.
+    Fit,
+    /// Scales each axis on its own so the logical area fills the whole window, distorting the
+    /// image when the window has a different aspect ratio. There are no bars.
+    Stretch,
+}
+
+/// The kind of window to create, mapped to SDL window flags. These are hints to the window
+/// manager: X11 honors all of them, Windows only knows utility (tool) windows and skipping the
+/// taskbar, and other platforms may ignore them entirely.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowKind {
+    Normal,
+    /// A floating tool window that is not shown in the taskbar (`SDL_WINDOW_UTILITY`).
+    Utility,
+    /// A tooltip window that is not shown in the taskbar (`SDL_WINDOW_TOOLTIP`).
+    Tooltip,
+    /// A popup menu window that is not shown in the taskbar (`SDL_WINDOW_POPUP_MENU`).
+    PopupMenu,
+}
+
 pub struct ApplicationGDXConfig {
-    fps: u8,
+    fps: u32,
     resizable: bool,
     screen_size: (u32, u32),
     title: String,
-    vsync: bool,
+    swap_interval: SwapInterval,
+    start_hidden: bool,
+    match_refresh_rate: bool,
+    sdl_hints: Vec<(String, String)>,
+    gl_versions: Vec<(u8, u8)>,
+    window_kind: WindowKind,
+    logical_size: Option<(u32, u32)>,
+    logical_scaling: LogicalScaling,
+    window_mode: WindowMode,
+    clear_color: [f32; 4],
+    max_frame_skip: u32,
+    frame_skip_threshold: f32,
+    delta_smoothing: usize,
+    msaa: u8,
+    update_rate: u32,
 }
 
 impl ApplicationGDXConfig {
@@ -13,16 +70,37 @@ impl ApplicationGDXConfig {
             screen_size: (800, 600),
             resizable: false,
             title: "Rust GDX Launcher".into(),
-            vsync: true,
+            swap_interval: SwapInterval::Vsync,
+            start_hidden: false,
+            match_refresh_rate: false,
+            sdl_hints: Vec::new(),
+            gl_versions: vec![(3, 3)],
+            window_kind: WindowKind::Normal,
+            logical_size: None,
+            logical_scaling: LogicalScaling::Fit,
+            window_mode: WindowMode::Windowed,
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            max_frame_skip: 0,
+            frame_skip_threshold: 0.25,
+            delta_smoothing: 0,
+            msaa: 0,
+            update_rate: 0,
         }
     }
 
-    pub fn with_fps(mut self, fps: u8) -> Self {
+    /// Target frame rate of the launcher loop. `0` means unlimited: the loop never sleeps and
+    /// frames are only paced by vsync, if enabled.
+    ///
+    /// With vsync the refresh rate wins over a higher cap. A lower cap that divides the refresh
+    /// rate, like 30 on a 60 Hz display, is reached by presenting every n-th refresh without
+    /// sleeping. Other caps are reached on average by sleeping, with uneven frame times and a
+    /// warning at launch.
+    pub fn with_fps(mut self, fps: u32) -> Self {
         self.fps = fps;
         self
     }
 
-    pub fn fps(&self) -> u8 {
+    pub fn fps(&self) -> u32 {
         self.fps
     }
 
@@ -54,11 +132,199 @@ impl ApplicationGDXConfig {
     }
 
     pub fn with_vsync(mut self, vsync: bool) -> Self {
-        self.vsync = vsync;
+        self.swap_interval = if vsync { SwapInterval::Vsync } else { SwapInterval::Immediate };
         self
     }
 
     pub fn vsync(&self) -> bool {
-        self.vsync
+        self.swap_interval != SwapInterval::Immediate
+    }
+
+    pub fn with_swap_interval(mut self, swap_interval: SwapInterval) -> Self {
+        self.swap_interval = swap_interval;
+        self
+    }
+
+    pub fn swap_interval(&self) -> SwapInterval {
+        self.swap_interval
+    }
+
+    /// Builds the window hidden; reveal it with `Graphics::show_window` once the first frame has
+    /// been rendered. Events keep arriving and `AppGDX::step` keeps running while hidden.
+    pub fn with_start_hidden(mut self, start_hidden: bool) -> Self {
+        self.start_hidden = start_hidden;
+        self
+    }
+
+    pub fn start_hidden(&self) -> bool {
+        self.start_hidden
+    }
+
+    /// Uses the refresh rate of the window's display as the frame target instead of `fps`,
+    /// falling back to 60 if SDL cannot report it. With vsync enabled the buffer swap already
+    /// paces frames to the refresh rate, so this mostly matters with vsync off.
+    pub fn with_match_refresh_rate(mut self, match_refresh_rate: bool) -> Self {
+        self.match_refresh_rate = match_refresh_rate;
+        self
+    }
+
+    pub fn match_refresh_rate(&self) -> bool {
+        self.match_refresh_rate
+    }
+
+    /// Sets an SDL hint such as `SDL_RENDER_SCALE_QUALITY` or `SDL_VIDEO_HIGHDPI_DISABLED`.
+    /// Hints are applied in order right before SDL is initialized, since most of them are only
+    /// read during initialization or window creation.
+    pub fn with_sdl_hint(mut self, key: &str, value: &str) -> Self {
+        self.sdl_hints.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn sdl_hints(&self) -> &[(String, String)] {
+        &self.sdl_hints
+    }
+
+    /// Core profile OpenGL versions to try in order of preference when creating the window.
+    /// The first one the driver accepts is used; `Graphics::gl_info` reports which one that was.
+    /// The built-in sprite, shape and text shaders need at least 3.3, so lower versions only
+    /// make sense for apps that bring their own shaders.
+    pub fn with_gl_versions(mut self, gl_versions: &[(u8, u8)]) -> Self {
+        assert!(!gl_versions.is_empty(), "At least one OpenGL version is required!");
+        self.gl_versions = gl_versions.to_vec();
+        self
+    }
+
+    pub fn gl_versions(&self) -> &[(u8, u8)] {
+        &self.gl_versions
+    }
+
+    pub fn with_window_kind(mut self, window_kind: WindowKind) -> Self {
+        self.window_kind = window_kind;
+        self
+    }
+
+    pub fn window_kind(&self) -> WindowKind {
+        self.window_kind
+    }
+
+    /// Designs the game for a fixed logical resolution; `Graphics::logical_projection` then maps
+    /// it onto any window size as set with `with_logical_scaling`.
+    pub fn with_logical_size(mut self, logical_size: (u32, u32)) -> Self {
+        self.logical_size = Some(logical_size);
+        self
+    }
+
+    pub fn logical_size(&self) -> Option<(u32, u32)> {
+        self.logical_size
+    }
+
+    /// How the logical size is mapped onto the window. Defaults to `LogicalScaling::Fit`.
+    pub fn with_logical_scaling(mut self, logical_scaling: LogicalScaling) -> Self {
+        self.logical_scaling = logical_scaling;
+        self
+    }
+
+    pub fn logical_scaling(&self) -> LogicalScaling {
+        self.logical_scaling
+    }
+
+    pub fn with_window_mode(mut self, window_mode: WindowMode) -> Self {
+        self.window_mode = window_mode;
+        self
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        self.window_mode
+    }
+
+    pub fn with_clear_color(mut self, clear_color: [f32; 4]) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    pub fn clear_color(&self) -> [f32; 4] {
+        self.clear_color
+    }
+
+    /// Lets the launcher skip rendering for up to `max_frame_skip` consecutive frames while the
+    /// game runs behind; `ApplicationGDX::should_render` tells `step` whether to draw. Logic keeps
+    /// updating every frame, so the game catches up at the cost of a choppier picture. Disabled
+    /// with the default of `0`, and always with an unlimited frame rate.
+    pub fn with_max_frame_skip(mut self, max_frame_skip: u32) -> Self {
+        self.max_frame_skip = max_frame_skip;
+        self
+    }
+
+    pub fn max_frame_skip(&self) -> u32 {
+        self.max_frame_skip
+    }
+
+    /// How far a frame has to overrun its target duration before the next one skips rendering, as
+    /// a fraction of the target duration. Defaults to `0.25`.
+    pub fn with_frame_skip_threshold(mut self, frame_skip_threshold: f32) -> Self {
+        self.frame_skip_threshold = frame_skip_threshold;
+        self
+    }
+
+    pub fn frame_skip_threshold(&self) -> f32 {
+        self.frame_skip_threshold
+    }
+
+    /// Number of frames `Time::smoothed_delta_time` averages over. Disabled with the default of
+    /// `0`.
+    pub fn with_delta_smoothing(mut self, frames: usize) -> Self {
+        self.delta_smoothing = frames;
+        self
+    }
+
+    pub fn delta_smoothing(&self) -> usize {
+        self.delta_smoothing
+    }
+
+    /// Number of samples per pixel for multisample antialiasing, which has to be a power of two.
+    /// Disabled with the default of `0`. If the GPU can't provide the requested samples, the
+    /// window is created without multisampling instead of failing; `Graphics::msaa_samples`
+    /// reports what the driver actually granted.
+    pub fn with_msaa(mut self, samples: u8) -> Self {
+        assert!(samples == 0 || samples.is_power_of_two(), "MSAA samples must be 0 or a power of two!");
+        self.msaa = samples;
+        self
+    }
+
+    pub fn msaa(&self) -> u8 {
+        self.msaa
+    }
+
+    /// Rate in Hz at which `AppGDX::fixed_step` runs, independently of the render rate. The
+    /// launcher accumulates the frame time and runs as many fixed steps per frame as fit, see
+    /// `ApplicationGDX::interpolation_alpha` for rendering in between. Disabled with the default
+    /// of `0`.
+    pub fn with_update_rate(mut self, update_rate: u32) -> Self {
+        self.update_rate = update_rate;
+        self
+    }
+
+    pub fn update_rate(&self) -> u32 {
+        self.update_rate
+    }
+
+    /// Same as `with_update_rate`, e.g. `with_fixed_fps(60)` for physics that needs a constant
+    /// delta time.
+    pub fn with_fixed_fps(self, fixed_fps: u32) -> Self {
+        self.with_update_rate(fixed_fps)
+    }
+
+    pub fn fixed_fps(&self) -> u32 {
+        self.update_rate
+    }
+
+    /// Rate in Hz at which `AppGDX::step` renders, the same as `with_fps`. `0` renders as fast as
+    /// possible or as vsync allows.
+    pub fn with_render_rate(self, render_rate: u32) -> Self {
+        self.with_fps(render_rate)
+    }
+
+    pub fn render_rate(&self) -> u32 {
+        self.fps
     }
 }
@@ -1,27 +1,179 @@
 use std::path::Path;
+use std::rc::Rc;
 
 use glium;
+use glium::{CapabilitiesSource, Surface};
 use glium_sdl2::{DisplayBuild, SDL2Facade};
 use image;
 use sdl2;
 
-use crate::config::ApplicationGDXConfig;
+use crate::config::{ApplicationGDXConfig, LogicalScaling, SwapInterval, WindowKind, WindowMode};
+use crate::graphics::render_target::RenderTarget;
+use crate::graphics::sprite::{SpriteBatch, SpriteDrawParams, SpriteRenderer};
+use crate::graphics::texture::{check_texture_size, TextureLoadError, TextureRegion};
 
 pub mod animation;
+pub mod camera;
+pub mod compressed;
+pub mod mesh;
+pub mod nine_patch;
+pub mod picking;
+pub mod post_process;
+pub mod render_target;
 pub mod shape;
 pub mod sprite;
+#[cfg(test)]
+pub(crate) mod test_support;
 pub mod text;
 pub mod texture;
+pub mod ui;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+/// Returns the largest rect with `target_aspect` that fits into a window of the given size,
+/// centered so the rest of the window is letterboxed.
+pub fn best_fit_size(target_aspect: f32, window: (u32, u32)) -> Rect {
+    let (width, height) = (window.0 as f32, window.1 as f32);
+    if height == 0.0 || target_aspect <= 0.0 {
+        return Rect::new(0.0, 0.0, width, height);
+    }
+
+    if width / height > target_aspect {
+        let fit_width = height * target_aspect;
+        Rect::new((width - fit_width) / 2.0, 0.0, fit_width, height)
+    } else {
+        let fit_height = width / target_aspect;
+        Rect::new(0.0, (height - fit_height) / 2.0, width, fit_height)
+    }
+}
+
+/// Projection that shows the whole `logical` area as large as possible in a window of the given
+/// size while keeping its aspect ratio. The logical area is centered with (0, 0) at its bottom-left
+/// and the leftover bars on two sides show what lies beyond its edges.
+pub fn logical_projection(logical: (u32, u32), window: (u32, u32)) -> glm::Mat4 {
+    let (logical_width, logical_height) = (logical.0 as f32, logical.1 as f32);
+    let (window_width, window_height) = (window.0 as f32, window.1 as f32);
+    if logical_width <= 0.0 || logical_height <= 0.0 || window_width <= 0.0 || window_height <= 0.0 {
+        return glm::ortho(0.0, logical_width, 0.0, logical_height, -1.0, 1.0);
+    }
+
+    let scale = (window_width / logical_width).min(window_height / logical_height);
+    let margin_x = (window_width / scale - logical_width) / 2.0;
+    let margin_y = (window_height / scale - logical_height) / 2.0;
+    glm::ortho(-margin_x, logical_width + margin_x, -margin_y, logical_height + margin_y, -1.0, 1.0)
+}
+
+/// The part of a window of the given size that shows the `logical` area, in pixels from the
+/// bottom-left like a GL viewport. `LogicalScaling::Stretch` always covers the whole window.
+pub fn logical_viewport(scaling: LogicalScaling, logical: (u32, u32), window: (u32, u32)) -> Rect {
+    match scaling {
+        LogicalScaling::Fit if logical.1 > 0 => best_fit_size(logical.0 as f32 / logical.1 as f32, window),
+        _ => Rect::new(0.0, 0.0, window.0 as f32, window.1 as f32),
+    }
+}
+
+/// The bars of a window of the given size around `viewport`, rounded to whole pixels.
+fn letterbox_bars(viewport: &Rect, window: (u32, u32)) -> Vec<glium::Rect> {
+    let left = (viewport.x.round() as u32).min(window.0);
+    let bottom = (viewport.y.round() as u32).min(window.1);
+    let right = ((viewport.x + viewport.width).round() as u32).clamp(left, window.0);
+    let top = ((viewport.y + viewport.height).round() as u32).clamp(bottom, window.1);
+
+    let bars = [
+        glium::Rect { left: 0, bottom: 0, width: left, height: window.1 },
+        glium::Rect { left: right, bottom: 0, width: window.0 - right, height: window.1 },
+        glium::Rect { left: 0, bottom: 0, width: window.0, height: bottom },
+        glium::Rect { left: 0, bottom: top, width: window.0, height: window.1 - top },
+    ];
+    bars.iter().copied().filter(|bar| bar.width > 0 && bar.height > 0).collect()
+}
+
+/// Converts a window position with the origin at the top-left, like `Input::mouse_pos`, into
+/// world coordinates of `projection`, which maps world space to normalized device coordinates with
+/// y pointing up as all renderers here expect. `screen_size` is the window size in pixels.
+pub fn unproject(projection: &glm::Mat4, screen_pos: (i32, i32), screen_size: (u32, u32)) -> (f32, f32) {
+    let ndc_x = 2.0 * screen_pos.0 as f32 / screen_size.0 as f32 - 1.0;
+    let ndc_y = 1.0 - 2.0 * screen_pos.1 as f32 / screen_size.1 as f32;
+    let world = glm::inverse(projection) * glm::vec4(ndc_x, ndc_y, 0.0, 1.0);
+    (world.x / world.w, world.y / world.w)
+}
+
+/// The inverse of `unproject`: the window position, origin at the top-left, of a point in world
+/// coordinates.
+pub fn project(projection: &glm::Mat4, world_pos: (f32, f32), screen_size: (u32, u32)) -> (f32, f32) {
+    let ndc = projection * glm::vec4(world_pos.0, world_pos.1, 0.0, 1.0);
+    let (ndc_x, ndc_y) = (ndc.x / ndc.w, ndc.y / ndc.w);
+    (
+        (ndc_x + 1.0) / 2.0 * screen_size.0 as f32,
+        (1.0 - ndc_y) / 2.0 * screen_size.1 as f32,
+    )
+}
+
+fn query_max_texture_size<F: glium::backend::Facade>(display: &F) -> u32 {
+    let max_texture_size = display.get_capabilities().max_texture_size;
+    if max_texture_size > 0 {
+        max_texture_size as u32
+    } else {
+        u32::MAX
+    }
+}
+
+fn rgba_pixels(image: image::DynamicImage) -> (Vec<u8>, (u32, u32)) {
+    let image = image.to_rgba();
+    let dimensions = image.dimensions();
+    (image.into_raw(), dimensions)
+}
+
+fn premultiply_alpha(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_mut(4) {
+        let alpha = pixel[3] as u16;
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u16 * alpha + 127) / 255) as u8;
+        }
+    }
+}
 
 pub struct Graphics {
     display: SDL2Facade,
+    video_subsystem: sdl2::VideoSubsystem,
+    swap_interval: SwapInterval,
+    max_texture_size: u32,
+    logical_size: Option<(u32, u32)>,
+    logical_scaling: LogicalScaling,
+    logical_projection: glm::Mat4,
+    logical_viewport: Rect,
+    window_mode: WindowMode,
+    clear_color: [f32; 4],
+    msaa_samples: u8,
 }
 
 impl Graphics {
+    /// Creates the window and clears it once, so it never shows undefined framebuffer contents
+    /// before the first frame. Apps still have to clear the frame themselves every `step`.
     pub fn new(config: &ApplicationGDXConfig, sdl_context: &sdl2::Sdl) -> Self {
         let video_subsystem = sdl_context.video().unwrap();
 
-        video_subsystem.gl_attr().set_context_version(3, 3);
         video_subsystem.gl_attr().set_context_profile(sdl2::video::GLProfile::Core);
 
         let screen_size = config.screen_size();
@@ -29,24 +181,169 @@ impl Graphics {
         if config.resizable() {
             window_builder.resizable();
         }
-        let display = window_builder
-            .build_glium()
-            .expect("Could not build glium window.");
-
-        let swap_interval = if config.vsync() { 1 } else { 0 };
-        video_subsystem.gl_set_swap_interval(swap_interval)
-            .expect("Could not set OpenGL swap interval.");
+        if config.start_hidden() {
+            window_builder.hidden();
+        }
+        match config.window_mode() {
+            WindowMode::Windowed => {}
+            WindowMode::Fullscreen => {
+                window_builder.fullscreen();
+            }
+            WindowMode::BorderlessFullscreen => {
+                window_builder.fullscreen_desktop();
+            }
+        }
+        let kind_flags = match config.window_kind() {
+            WindowKind::Normal => 0,
+            WindowKind::Utility => sdl2::sys::SDL_WindowFlags::SDL_WINDOW_UTILITY as u32,
+            WindowKind::Tooltip => sdl2::sys::SDL_WindowFlags::SDL_WINDOW_TOOLTIP as u32,
+            WindowKind::PopupMenu => sdl2::sys::SDL_WindowFlags::SDL_WINDOW_POPUP_MENU as u32,
+        };
+        if kind_flags != 0 {
+            let skip_taskbar = sdl2::sys::SDL_WindowFlags::SDL_WINDOW_SKIP_TASKBAR as u32;
+            let flags = window_builder.window_flags() | kind_flags | skip_taskbar;
+            window_builder.set_window_flags(flags);
+        }
+        let msaa_options: &[u8] = if config.msaa() > 0 { &[config.msaa(), 0] } else { &[0] };
+        let mut errors = Vec::new();
+        let mut display = None;
+        'msaa: for &samples in msaa_options {
+            video_subsystem.gl_attr().set_multisample_buffers(if samples > 0 { 1 } else { 0 });
+            video_subsystem.gl_attr().set_multisample_samples(samples);
+            for &(major, minor) in config.gl_versions() {
+                video_subsystem.gl_attr().set_context_version(major, minor);
+                match window_builder.build_glium() {
+                    Ok(built) => {
+                        display = Some(built);
+                        break 'msaa;
+                    }
+                    Err(e) => errors.push(format!("{}.{} ({}x MSAA): {:?}", major, minor, samples, e)),
+                }
+            }
+        }
+        let display = display
+            .unwrap_or_else(|| panic!("Could not build glium window with any requested OpenGL version: {}",
+                                      errors.join(", ")));
+        // Queries `GL_SAMPLES` of the new context, which may differ from the requested count.
+        let msaa_samples = video_subsystem.gl_attr().multisample_samples();
 
-        Self {
+        let max_texture_size = query_max_texture_size(&display);
+        let mut graphics = Self {
             display,
+            video_subsystem,
+            swap_interval: SwapInterval::Immediate,
+            max_texture_size,
+            logical_size: config.logical_size(),
+            logical_scaling: config.logical_scaling(),
+            logical_projection: glm::identity(),
+            logical_viewport: Rect::default(),
+            window_mode: config.window_mode(),
+            clear_color: config.clear_color(),
+            msaa_samples,
+        };
+
+        let mut frame = graphics.display.draw();
+        graphics.clear(&mut frame);
+        frame.finish()
+            .expect("Could not clear the initial frame.");
+
+        graphics.set_vsync(config.swap_interval());
+        graphics.resize();
+
+        graphics
+    }
+
+    /// Sets the swap interval and returns the one that took effect. `SwapInterval::Adaptive`
+    /// falls back to `SwapInterval::Vsync` on drivers without late swap tearing support.
+    pub fn set_vsync(&mut self, swap_interval: SwapInterval) -> SwapInterval {
+        let result = match swap_interval {
+            SwapInterval::Immediate => self.video_subsystem.gl_set_swap_interval(0),
+            SwapInterval::Vsync => self.video_subsystem.gl_set_swap_interval(1),
+            SwapInterval::Adaptive => self.video_subsystem.gl_set_swap_interval(-1),
+        };
+        self.swap_interval = match result {
+            Ok(()) => swap_interval,
+            Err(_) if swap_interval == SwapInterval::Adaptive => {
+                self.video_subsystem.gl_set_swap_interval(1)
+                    .expect("Could not set OpenGL swap interval.");
+                SwapInterval::Vsync
+            }
+            Err(e) => panic!("Could not set OpenGL swap interval: {}", e),
+        };
+
+        self.swap_interval
+    }
+
+    /// Presents every `frames`-th refresh, which SDL's safe API can't express beyond `1`. Keeps
+    /// `swap_interval` as is and leaves the interval unchanged if the driver rejects it.
+    pub(crate) fn set_swap_interval_frames(&mut self, frames: u32) -> Result<(), String> {
+        // SAFETY: SDL_GL_SetSwapInterval only reads its integer argument and applies it to the GL
+        // context current on this thread, which `self.display` created and keeps alive. A missing
+        // context or an unsupported interval is reported through the return value.
+        let result = unsafe { sdl2::sys::SDL_GL_SetSwapInterval(frames as i32) };
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(sdl2::get_error())
         }
     }
 
+    pub fn set_clear_color(&mut self, clear_color: [f32; 4]) {
+        self.clear_color = clear_color;
+    }
+
+    pub fn clear_color(&self) -> [f32; 4] {
+        self.clear_color
+    }
+
+    /// Clears `target`, usually the frame returned by `display().draw()`, to the clear color.
+    pub fn clear<S: Surface>(&self, target: &mut S) {
+        let [r, g, b, a] = self.clear_color;
+        target.clear_color(r, g, b, a);
+    }
+
+    /// Like `clear`, but also clears the depth buffer to `depth`.
+    pub fn clear_with_depth<S: Surface>(&self, target: &mut S, depth: f32) {
+        let [r, g, b, a] = self.clear_color;
+        target.clear_color_and_depth((r, g, b, a), depth);
+    }
+
+    pub fn swap_interval(&self) -> SwapInterval {
+        self.swap_interval
+    }
+
+    /// The OpenGL version of the context that was actually created.
+    pub fn gl_info(&self) -> glium::Version {
+        *self.display.get_opengl_version()
+    }
+
     pub fn set_size(&mut self, width: u32, height: u32) {
         self.display.window_mut().set_size(width, height)
             .unwrap();
     }
 
+    /// Switches the window mode at runtime. The framebuffer size changes along with it, which the
+    /// launcher picks up at the next frame and reports through `AppGDX::resize`. Keeps the
+    /// current mode and returns SDL's error if the switch fails.
+    pub fn set_window_mode(&mut self, window_mode: WindowMode) -> Result<(), String> {
+        let fullscreen_type = match window_mode {
+            WindowMode::Windowed => sdl2::video::FullscreenType::Off,
+            WindowMode::Fullscreen => sdl2::video::FullscreenType::True,
+            WindowMode::BorderlessFullscreen => sdl2::video::FullscreenType::Desktop,
+        };
+        self.display.window_mut().set_fullscreen(fullscreen_type)?;
+        self.window_mode = window_mode;
+        Ok(())
+    }
+
+    pub fn window_mode(&self) -> WindowMode {
+        self.window_mode
+    }
+
+    pub fn show_window(&mut self) {
+        self.display.window_mut().show();
+    }
+
     pub fn display(&self) -> &SDL2Facade {
         &self.display
     }
@@ -55,17 +352,276 @@ impl Graphics {
         self.display.get_framebuffer_dimensions()
     }
 
-    pub fn load_texture<P: AsRef<Path>>(&self, path: P, reversed: bool) -> glium::Texture2d {
-        let image = image::open(path).unwrap().to_rgba();
-        let image_dimensions = image.dimensions();
+    pub fn refresh_rate(&self) -> Option<u32> {
+        let display_index = self.display.window().display_index().ok()?;
+        let display_mode = self.video_subsystem.current_display_mode(display_index).ok()?;
+        if display_mode.refresh_rate > 0 {
+            Some(display_mode.refresh_rate as u32)
+        } else {
+            None
+        }
+    }
+
+    pub fn logical_size(&self) -> Option<(u32, u32)> {
+        self.logical_size
+    }
+
+    /// Projection for the logical size set with `ApplicationGDXConfig::with_logical_size`, mapped
+    /// onto the window as set with `ApplicationGDXConfig::with_logical_scaling`. It is updated
+    /// when the window is resized. With `LogicalScaling::Fit` the projection covers the whole
+    /// window, so sprites past the logical edges show up in the bars until `draw_letterbox`
+    /// covers them. Without a logical size this is a pixel projection of the window.
+    pub fn logical_projection(&self) -> glm::Mat4 {
+        self.logical_projection
+    }
+
+    /// The part of the window showing the logical area, in pixels from the bottom-left. Covers
+    /// the whole window without a logical size or with `LogicalScaling::Stretch`.
+    pub fn logical_viewport(&self) -> Rect {
+        self.logical_viewport
+    }
+
+    /// Clears the bars around `logical_viewport` to black. Call it on the frame after drawing
+    /// the scene and before `finish`; it does nothing when there are no bars.
+    pub fn draw_letterbox<S: Surface>(&self, target: &mut S) {
+        let (width, height) = target.get_dimensions();
+        for bar in letterbox_bars(&self.logical_viewport, (width, height)) {
+            target.clear(Some(&bar), Some((0.0, 0.0, 0.0, 1.0)), false, None, None);
+        }
+    }
+
+    /// Recomputes the logical projection and viewport for the current window size.
+    pub(crate) fn resize(&mut self) {
+        let screen_size = self.screen_size();
+        let logical_size = self.logical_size.unwrap_or(screen_size);
+        self.logical_projection = match self.logical_scaling {
+            LogicalScaling::Fit => logical_projection(logical_size, screen_size),
+            LogicalScaling::Stretch =>
+                glm::ortho(0.0, logical_size.0 as f32, 0.0, logical_size.1 as f32, -1.0, 1.0),
+        };
+        self.logical_viewport = logical_viewport(self.logical_scaling, logical_size, screen_size);
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        let (width, height) = self.screen_size();
+        if height == 0 {
+            return 0.0;
+        }
+        width as f32 / height as f32
+    }
+
+    /// The largest width or height a texture may have on this GPU (`GL_MAX_TEXTURE_SIZE`).
+    pub fn max_texture_size(&self) -> u32 {
+        self.max_texture_size
+    }
+
+    /// Multisample count the driver granted the window (`GL_SAMPLES`); `0` if MSAA is disabled or
+    /// the requested count was not available.
+    pub fn msaa_samples(&self) -> u8 {
+        self.msaa_samples
+    }
+
+    pub fn load_texture<P: AsRef<Path>>(&self, path: P, reversed: bool) -> Result<glium::Texture2d, TextureLoadError> {
+        self.create_texture(path, reversed, false)
+    }
+
+    /// Like `load_texture`, but panics with the load error instead of returning it.
+    pub fn load_texture_or_panic<P: AsRef<Path>>(&self, path: P, reversed: bool) -> glium::Texture2d {
+        let path = path.as_ref();
+        self.load_texture(path, reversed)
+            .unwrap_or_else(|e| panic!("Could not load texture {}: {}", path.display(), e))
+    }
+
+    /// Loads a texture from an encoded image in memory, e.g. embedded with `include_bytes!`. The
+    /// format is detected from the data like with `load_texture`.
+    pub fn load_texture_from_memory(&self, bytes: &[u8], reversed: bool) -> Result<glium::Texture2d, TextureLoadError> {
+        self.create_texture_from_image(image::load_from_memory(bytes)?, reversed, false)
+    }
+
+    /// Loads a DDS or KTX file with S3TC compressed data straight into a compressed texture,
+    /// see `compressed` for the supported formats. Draw it with `SpriteRenderer::draw_compressed`.
+    pub fn load_compressed_texture<P: AsRef<Path>>(&self, path: P) -> Result<glium::texture::CompressedTexture2d,
+                                                                        compressed::CompressedTextureError> {
+        compressed::load_compressed_texture(&self.display, path)
+    }
+
+    /// Loads a texture with each pixel's RGB multiplied by its alpha, ready to be drawn with a
+    /// premultiplied blend function (`One`, `OneMinusSourceAlpha`) without dark halos.
+    pub fn load_texture_premultiplied<P: AsRef<Path>>(&self, path: P,
+                                                      reversed: bool) -> Result<glium::Texture2d, TextureLoadError> {
+        self.create_texture(path, reversed, true)
+    }
+
+    /// Creates a texture from tightly packed 8-bit RGBA pixels, given row by row from the top.
+    /// Fails if `pixels` does not hold exactly `width * height * 4` bytes.
+    pub fn texture_from_pixels(&self, pixels: &[u8], width: u32,
+                               height: u32) -> Result<Rc<glium::Texture2d>, TextureLoadError> {
+        check_texture_size((width, height), self.max_texture_size)?;
+        texture::texture_from_pixels(&self.display, pixels, width, height)
+    }
+
+    /// Reads back the last presented frame with the top-left pixel first. Its size is the
+    /// framebuffer size of that frame, which matches `screen_size` once a frame has been drawn
+    /// after a resize.
+    pub fn read_framebuffer(&self) -> image::RgbaImage {
+        let raw: glium::texture::RawImage2d<u8> = self.display.read_front_buffer()
+            .expect("Could not read the front buffer.");
+        let image = image::RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned())
+            .expect("Front buffer data does not match its dimensions.");
+        image::imageops::flip_vertical(&image)
+    }
+
+    /// Saves the last presented frame, see `read_framebuffer`. The format is chosen from the
+    /// file extension, e.g. `.png`.
+    pub fn save_screenshot<P: AsRef<Path>>(&self, path: P) -> image::ImageResult<()> {
+        self.read_framebuffer().save(path)
+    }
+
+    /// Renders once into a new `width` x `height` texture and returns it as a region. `draw` gets
+    /// a batch over the texture with an alpha-blending projection of its pixels, (0, 0) at the
+    /// bottom-left; `renderer`'s own projection is restored afterwards. The texture lives on the
+    /// GPU of this window's GL context, so the region must not outlive `Graphics`.
+    pub fn bake<F>(&self, width: u32, height: u32, renderer: &mut SpriteRenderer,
+                   draw: F) -> Result<TextureRegion, glium::DrawError>
+    where
+        F: FnOnce(&mut SpriteBatch<glium::framebuffer::SimpleFrameBuffer>) -> Result<(), glium::DrawError>,
+    {
+        let target = RenderTarget::new(&self.display, width, height);
+
+        let projection = renderer.get_projection_matrix();
+        let view_offset = renderer.view_offset();
+        renderer.set_projection_matrix(target.projection());
+        renderer.set_view_offset(glm::vec2(0.0, 0.0));
+        let result = {
+            let mut framebuffer = target.surface();
+            framebuffer.clear_color(0.0, 0.0, 0.0, 0.0);
+
+            let mut batch = renderer.begin_batch(SpriteDrawParams::new().alpha(true), &mut framebuffer);
+            let result = draw(&mut batch);
+            batch.finish().and(result)
+        };
+        renderer.set_projection_matrix(projection);
+        renderer.set_view_offset(view_offset);
+        result?;
+
+        Ok(target.region())
+    }
+
+    fn create_texture<P: AsRef<Path>>(&self, path: P, reversed: bool,
+                                      premultiply: bool) -> Result<glium::Texture2d, TextureLoadError> {
+        self.create_texture_from_image(image::open(path)?, reversed, premultiply)
+    }
+
+    fn create_texture_from_image(&self, image: image::DynamicImage, reversed: bool,
+                                 premultiply: bool) -> Result<glium::Texture2d, TextureLoadError> {
+        let (mut pixels, image_dimensions) = rgba_pixels(image);
+        check_texture_size(image_dimensions, self.max_texture_size)?;
+        if premultiply {
+            premultiply_alpha(&mut pixels);
+        }
         let image = if reversed {
-            glium::texture::RawImage2d::from_raw_rgba_reversed(&image.into_raw(), image_dimensions)
+            glium::texture::RawImage2d::from_raw_rgba_reversed(&pixels, image_dimensions)
         } else {
-            glium::texture::RawImage2d::from_raw_rgba(image.into_raw(), image_dimensions)
+            glium::texture::RawImage2d::from_raw_rgba(pixels, image_dimensions)
         };
-        glium::Texture2d::new(&self.display, image).unwrap()
+        Ok(glium::Texture2d::new(&self.display, image)?)
     }
 
     fn draw(&self) {
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graphics::sprite::Sprite;
+    use crate::graphics::test_support::hidden_graphics;
+
+    #[test]
+    fn logical_projection_fits_the_logical_area() {
+        let project = |projection: &glm::Mat4, x: f32, y: f32| {
+            let ndc = projection * glm::vec4(x, y, 0.0, 1.0);
+            (ndc.x, ndc.y)
+        };
+
+        let wide = logical_projection((1920, 1080), (1000, 400));
+        let (left, bottom) = project(&wide, 0.0, 0.0);
+        let (right, top) = project(&wide, 1920.0, 1080.0);
+        assert!((bottom + 1.0).abs() < 1e-5 && (top - 1.0).abs() < 1e-5);
+        assert!(left > -1.0 && right < 1.0);
+        assert!((left + right).abs() < 1e-5);
+
+        let tall = logical_projection((1920, 1080), (400, 1000));
+        let (left, bottom) = project(&tall, 0.0, 0.0);
+        let (right, top) = project(&tall, 1920.0, 1080.0);
+        assert!((left + 1.0).abs() < 1e-5 && (right - 1.0).abs() < 1e-5);
+        assert!(bottom > -1.0 && top < 1.0);
+    }
+
+    #[test]
+    fn fit_letterboxes_and_stretch_fills_the_window() {
+        let fit = logical_viewport(LogicalScaling::Fit, (160, 90), (400, 300));
+        assert_eq!(fit, Rect::new(0.0, 37.5, 400.0, 225.0));
+        assert_eq!(letterbox_bars(&fit, (400, 300)), vec![
+            glium::Rect { left: 0, bottom: 0, width: 400, height: 38 },
+            glium::Rect { left: 0, bottom: 263, width: 400, height: 37 },
+        ]);
+
+        let stretch = logical_viewport(LogicalScaling::Stretch, (160, 90), (400, 300));
+        assert_eq!(stretch, Rect::new(0.0, 0.0, 400.0, 300.0));
+        assert!(letterbox_bars(&stretch, (400, 300)).is_empty());
+    }
+
+    #[test]
+    #[ignore]
+    fn clear_shows_the_clear_color_behind_a_sprite_batch() {
+        let clear_color = [0.2, 0.4, 0.6, 1.0];
+        let (_sdl_context, graphics) = hidden_graphics(ApplicationGDXConfig::new().with_clear_color(clear_color));
+        let (width, height) = graphics.screen_size();
+        let projection = glm::ortho(0.0, width as f32, 0.0, height as f32, -1.0, 1.0);
+        let mut renderer = SpriteRenderer::new(graphics.display(), projection);
+        let mut sprite = Sprite::new(graphics.texture_from_pixels(&[255; 2 * 2 * 4], 2, 2).unwrap());
+        sprite.set_position(2.0, 2.0);
+
+        let mut frame = graphics.display().draw();
+        graphics.clear(&mut frame);
+        let mut batch = renderer.begin_batch(SpriteDrawParams::new(), &mut frame);
+        batch.draw(&sprite).unwrap();
+        batch.finish().unwrap();
+        frame.finish().unwrap();
+
+        let screenshot = graphics.read_framebuffer();
+        let expected = clear_color.iter().map(|&c| (c * 255.0).round() as i32);
+        let background = screenshot.get_pixel(width - 1, 0);
+        assert!(background.0.iter().zip(expected).all(|(&actual, expected)| (actual as i32 - expected).abs() <= 1),
+                "background is {:?}, expected the clear color {:?}", background, clear_color);
+        assert_eq!(screenshot.get_pixel(2, height - 3).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn unproject_flips_the_window_y_axis() {
+        let projection = glm::ortho(0.0, 400.0, 0.0, 200.0, -1.0, 1.0);
+        let close = |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3;
+
+        assert!(close(unproject(&projection, (0, 0), (800, 600)), (0.0, 200.0)));
+        assert!(close(unproject(&projection, (800, 600), (800, 600)), (400.0, 0.0)));
+        assert!(close(unproject(&projection, (200, 450), (800, 600)), (100.0, 50.0)));
+        assert!(close(project(&projection, (100.0, 50.0), (800, 600)), (200.0, 450.0)));
+    }
+
+    #[test]
+    fn embedded_images_decode_to_rgba() {
+        let image = image::load_from_memory(include_bytes!("../assets/magenta_2x3.png")).unwrap();
+        let (pixels, dimensions) = rgba_pixels(image);
+        assert_eq!(dimensions, (2, 3));
+        assert_eq!(pixels.len(), 2 * 3 * 4);
+        assert_eq!(&pixels[..4], &[255, 0, 255, 255]);
+    }
+
+    #[test]
+    fn premultiply_alpha_scales_rgb_by_alpha() {
+        let mut pixels = vec![200, 100, 50, 128, 255, 255, 255, 255, 10, 20, 30, 0];
+        premultiply_alpha(&mut pixels);
+        assert_eq!(pixels, vec![100, 50, 25, 128, 255, 255, 255, 255, 0, 0, 0, 0]);
+    }
+}
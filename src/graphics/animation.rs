@@ -1,21 +1,27 @@
 use std::cmp;
+use std::rc::Rc;
 
 use crate::graphics::texture::TextureRegion;
+use crate::time::Time;
 
-
+#[derive(Clone, Copy, Debug)]
 pub enum PlayMode {
     Normal,
     Reversed,
     Loop,
     LoopReversed,
     LoopPingPong,
+    /// Like `LoopPingPong`, but lingers on the first and last frame for an extra `hold` seconds
+    /// before reversing.
+    LoopPingPongHold { hold: f32 },
 }
 
 pub struct Animation {
-    frame_duration: f32,
+    frame_durations: Vec<f32>,
     animation_duration: f32,
     key_frames: Vec<TextureRegion>,
-    pub play_mode: PlayMode,
+    events: Vec<(usize, String)>,
+    play_mode: PlayMode,
 }
 
 impl Animation {
@@ -24,47 +30,497 @@ impl Animation {
             return None;
         }
 
+        Self::with_frame_durations(&vec![frame_duration; key_frames.len()], key_frames)
+    }
+
+    /// Like `new`, but shows each key frame for its own duration. Ping-pong modes show a frame for
+    /// the same duration in both directions. Returns `None` if the lengths differ or a duration
+    /// isn't positive.
+    pub fn with_frame_durations(durations: &[f32], key_frames: &[TextureRegion]) -> Option<Self> {
+        if durations.len() != key_frames.len() || durations.iter().any(|&duration| duration <= 0.0) {
+            return None;
+        }
+
         Some(Self {
-            frame_duration,
-            animation_duration: frame_duration * key_frames.len() as f32,
+            frame_durations: durations.to_vec(),
+            animation_duration: durations.iter().sum(),
             key_frames: key_frames.to_vec(),
+            events: Vec::new(),
             play_mode: PlayMode::Normal,
         })
     }
 
+    /// Plays the animation in `play_mode` instead of the default `PlayMode::Normal`. Since `new`
+    /// returns an `Option`, chain it with `Animation::new(..).map(|a| a.with_play_mode(mode))`.
+    pub fn with_play_mode(mut self, play_mode: PlayMode) -> Self {
+        self.play_mode = play_mode;
+        self
+    }
+
+    pub fn set_play_mode(&mut self, play_mode: PlayMode) {
+        self.play_mode = play_mode;
+    }
+
+    pub fn play_mode(&self) -> PlayMode {
+        self.play_mode
+    }
+
     pub fn current_key_frame(&self, run_time: f32) -> &TextureRegion {
-        let num_frames = self.key_frames.len() as u32;
-        if num_frames == 1 {
-            return &self.key_frames[0];
+        &self.key_frames[self.key_frame_index(run_time)]
+    }
+
+    pub fn key_frame_index(&self, run_time: f32) -> usize {
+        if self.key_frames.len() <= 1 {
+            return 0;
         }
 
-        let frame_number = (run_time / self.frame_duration) as u32;
-        let frame_number = match self.play_mode {
-            PlayMode::Normal => cmp::min(num_frames - 1, frame_number),
-            PlayMode::Loop => frame_number % num_frames,
-            PlayMode::LoopPingPong => {
-                let frame_number = frame_number % ((num_frames * 2) - 2);
-                if frame_number >= num_frames {
-                    num_frames - 2 - (frame_number - num_frames)
-                } else {
-                    frame_number
-                }
-            },
-            PlayMode::Reversed => cmp::max(num_frames - frame_number - 1, 0),
-            PlayMode::LoopReversed => {
-                let frame_number = frame_number % num_frames;
-                num_frames - frame_number - 1
-            },
-        };
+        key_frame_index(&self.play_mode, &self.frame_durations, run_time) as usize
+    }
+
+    /// Same as `key_frame_index`, the index into `key_frames` shown at `run_time`.
+    pub fn current_frame_index(&self, run_time: f32) -> usize {
+        self.key_frame_index(run_time)
+    }
+
+    /// Whether a `Normal` or `Reversed` animation has shown its last key frame for its full
+    /// duration. Always `false` for looping play modes.
+    pub fn is_animation_finished(&self, run_time: f32) -> bool {
+        is_finished(&self.play_mode, self.animation_duration, run_time)
+    }
 
-        &self.key_frames[frame_number as usize]
+    /// Time within the current cycle of a looping play mode, where a ping-pong cycle covers both
+    /// directions including any hold. Non-looping modes clamp to the animation duration instead.
+    pub fn loop_time(&self, run_time: f32) -> f32 {
+        loop_time(&self.play_mode, &self.frame_durations, run_time)
+    }
+
+    /// Attaches a named event that fires whenever playback enters the key frame at `frame_index`.
+    pub fn add_event(&mut self, frame_index: usize, name: &str) {
+        assert!(frame_index < self.key_frames.len(), "Frame index {} is out of range!", frame_index);
+        self.events.push((frame_index, name.to_string()));
+    }
+
+    /// Returns the events of all key frames entered after `prev_run_time` up to and including
+    /// `cur_run_time`, in order, following loop and ping-pong wraps. A `prev_run_time` below zero
+    /// includes the first frame at time zero.
+    pub fn events_between(&self, prev_run_time: f32, cur_run_time: f32) -> Vec<&str> {
+        if self.events.is_empty() {
+            return Vec::new();
+        }
+
+        frames_entered(&self.play_mode, &self.frame_durations, prev_run_time, cur_run_time)
+            .into_iter()
+            .flat_map(|frame| {
+                self.events.iter()
+                    .filter(move |&&(index, _)| index == frame as usize)
+                    .map(|(_, name)| name.as_str())
+            })
+            .collect()
     }
 
     pub fn key_frames(&self) -> &[TextureRegion] {
         self.key_frames.as_slice()
     }
 
+    pub fn frame_durations(&self) -> &[f32] {
+        self.frame_durations.as_slice()
+    }
+
     pub fn animation_duration(&self) -> f32 {
         self.animation_duration
     }
 }
+
+/// Walks one pass of the play mode's sequence, holding its last entry once a non-looping mode ran
+/// through.
+fn key_frame_index(play_mode: &PlayMode, durations: &[f32], run_time: f32) -> u32 {
+    let len = sequence_len(play_mode, durations.len() as u32);
+    let mut time = if is_looping(play_mode) {
+        run_time.rem_euclid(cycle_duration(play_mode, durations))
+    } else {
+        run_time
+    };
+
+    for i in 0..len {
+        let (frame, duration) = sequence_entry(play_mode, durations, i);
+        if time < duration {
+            return frame;
+        }
+        time -= duration;
+    }
+    sequence_entry(play_mode, durations, len - 1).0
+}
+
+fn cycle_duration(play_mode: &PlayMode, durations: &[f32]) -> f32 {
+    (0..sequence_len(play_mode, durations.len() as u32))
+        .map(|i| sequence_entry(play_mode, durations, i).1)
+        .sum()
+}
+
+fn loop_time(play_mode: &PlayMode, durations: &[f32], run_time: f32) -> f32 {
+    if durations.is_empty() {
+        return 0.0;
+    }
+    let cycle = cycle_duration(play_mode, durations);
+    if cycle <= 0.0 {
+        return 0.0;
+    }
+
+    match *play_mode {
+        PlayMode::Normal | PlayMode::Reversed => run_time.max(0.0).min(cycle),
+        _ => run_time.rem_euclid(cycle),
+    }
+}
+
+fn is_finished(play_mode: &PlayMode, animation_duration: f32, run_time: f32) -> bool {
+    !is_looping(play_mode) && run_time >= animation_duration
+}
+
+fn is_looping(play_mode: &PlayMode) -> bool {
+    match *play_mode {
+        PlayMode::Normal | PlayMode::Reversed => false,
+        _ => true,
+    }
+}
+
+fn sequence_len(play_mode: &PlayMode, num_frames: u32) -> u32 {
+    match *play_mode {
+        PlayMode::Normal | PlayMode::Reversed | PlayMode::Loop | PlayMode::LoopReversed => num_frames,
+        PlayMode::LoopPingPong => cmp::max(num_frames * 2, 3) - 2,
+        PlayMode::LoopPingPongHold { .. } => num_frames * 2 - 1,
+    }
+}
+
+/// The key frame shown by entry `i` of one pass through the animation and how long it is shown.
+fn sequence_entry(play_mode: &PlayMode, durations: &[f32], i: u32) -> (u32, f32) {
+    let num_frames = durations.len() as u32;
+    let frame = match *play_mode {
+        PlayMode::Normal | PlayMode::Loop => i,
+        PlayMode::Reversed | PlayMode::LoopReversed => num_frames - 1 - i,
+        PlayMode::LoopPingPong | PlayMode::LoopPingPongHold { .. } if i < num_frames => i,
+        PlayMode::LoopPingPong => num_frames * 2 - 2 - i,
+        PlayMode::LoopPingPongHold { hold } => {
+            if i < num_frames * 2 - 2 {
+                num_frames * 2 - 2 - i
+            } else {
+                return (0, hold.max(0.0));
+            }
+        }
+    };
+
+    let duration = durations[frame as usize];
+    match *play_mode {
+        PlayMode::LoopPingPongHold { hold } if i + 1 == num_frames => (frame, duration + hold.max(0.0)),
+        _ => (frame, duration),
+    }
+}
+
+fn frames_entered(play_mode: &PlayMode, durations: &[f32], prev_run_time: f32, cur_run_time: f32) -> Vec<u32> {
+    let mut frames = Vec::new();
+    if durations.is_empty() || cur_run_time <= prev_run_time {
+        return frames;
+    }
+    let cycle = cycle_duration(play_mode, durations);
+    if cycle <= 0.0 {
+        return frames;
+    }
+
+    let looping = is_looping(play_mode);
+    let len = sequence_len(play_mode, durations.len() as u32);
+    let mut time = if looping {
+        (prev_run_time / cycle).floor().max(0.0) * cycle
+    } else {
+        0.0
+    };
+    let mut i = 0;
+    let mut last_frame = None;
+    while time <= cur_run_time {
+        let (frame, duration) = sequence_entry(play_mode, durations, i);
+        if time > prev_run_time && last_frame != Some(frame) {
+            frames.push(frame);
+        }
+        last_frame = Some(frame);
+        time += duration;
+
+        i += 1;
+        if i == len {
+            if !looping {
+                break;
+            }
+            i = 0;
+        }
+    }
+
+    frames
+}
+
+/// Which delta time an `AnimationController` advances with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeSource {
+    /// Follows `Time::delta_time`, so slow motion and pauses affect the animation.
+    Scaled,
+    /// Follows `Time::unscaled_delta_time`, e.g. for UI animations that keep playing while the
+    /// game is paused.
+    Unscaled,
+}
+
+/// Tracks the playback state of an `Animation`. Uses `TimeSource::Scaled` by default.
+pub struct AnimationController {
+    state_time: f32,
+    paused: bool,
+    time_source: TimeSource,
+}
+
+impl AnimationController {
+    pub fn new() -> Self {
+        Self {
+            state_time: 0.0,
+            paused: false,
+            time_source: TimeSource::Scaled,
+        }
+    }
+
+    pub fn set_time_source(&mut self, time_source: TimeSource) {
+        self.time_source = time_source;
+    }
+
+    pub fn time_source(&self) -> TimeSource {
+        self.time_source
+    }
+
+    pub fn update(&mut self, time: &Time) {
+        let delta_time = match self.time_source {
+            TimeSource::Scaled => time.delta_time(),
+            TimeSource::Unscaled => time.unscaled_delta_time(),
+        };
+        self.advance(delta_time as f32);
+    }
+
+    pub fn advance(&mut self, delta_time: f32) {
+        if !self.paused {
+            self.state_time += delta_time;
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn reset(&mut self) {
+        self.state_time = 0.0;
+    }
+
+    pub fn state_time(&self) -> f32 {
+        self.state_time
+    }
+
+    /// Like `update`, but also returns the events of the key frames entered during this update.
+    pub fn update_with_events<'a>(&mut self, time: &Time, animation: &'a Animation) -> Vec<&'a str> {
+        let prev_state_time = self.state_time;
+        self.update(time);
+        animation.events_between(prev_state_time, self.state_time)
+    }
+
+    pub fn current_key_frame<'a>(&self, animation: &'a Animation) -> &'a TextureRegion {
+        animation.current_key_frame(self.state_time)
+    }
+}
+
+/// Plays a uniform grid sheet like an 8x8 explosion frame by frame, row by row from the top-left
+/// of the image. Lighter than an `Animation` since regions are only created on demand.
+pub struct Flipbook {
+    texture: Rc<glium::Texture2d>,
+    cols: u32,
+    rows: u32,
+    fps: f32,
+    looping: bool,
+}
+
+impl Flipbook {
+    /// Returns `None` if the grid is empty or `fps` isn't positive. Loops by default.
+    pub fn new(texture: Rc<glium::Texture2d>, cols: u32, rows: u32, fps: f32) -> Option<Self> {
+        if cols == 0 || rows == 0 || fps <= 0.0 {
+            return None;
+        }
+
+        Some(Self {
+            texture,
+            cols,
+            rows,
+            fps,
+            looping: true,
+        })
+    }
+
+    /// Without looping the last frame is held once the flipbook ran through.
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn looping(&self) -> bool {
+        self.looping
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.cols * self.rows
+    }
+
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.frame_count() as f32 / self.fps
+    }
+
+    pub fn frame_index(&self, run_time: f32) -> u32 {
+        flipbook_frame(self.fps, self.frame_count(), self.looping, run_time)
+    }
+
+    pub fn region_at(&self, run_time: f32) -> TextureRegion {
+        let (offset, size) = flipbook_cell(self.frame_index(run_time), self.cols, self.rows,
+                                           self.texture.dimensions());
+        TextureRegion::with_sub_field(self.texture.clone(), offset, size)
+    }
+}
+
+fn flipbook_frame(fps: f32, frame_count: u32, looping: bool, run_time: f32) -> u32 {
+    let frame = (run_time.max(0.0) * fps) as u32;
+    if looping {
+        frame % frame_count
+    } else {
+        cmp::min(frame, frame_count - 1)
+    }
+}
+
+/// Offset and size of cell `index` counted from the top-left. Textures are stored bottom row
+/// first, so rows are flipped.
+fn flipbook_cell(index: u32, cols: u32, rows: u32, texture_size: (u32, u32)) -> ((u32, u32), (u32, u32)) {
+    let size = (texture_size.0 / cols, texture_size.1 / rows);
+    let (col, row) = (index % cols, index / cols);
+    ((col * size.0, texture_size.1 - (row + 1) * size.1), size)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ping_pong_hold_dwells_on_end_frames() {
+        let mode = PlayMode::LoopPingPongHold { hold: 0.25 };
+        let frames = |times: &[f32]| -> Vec<u32> {
+            times.iter().map(|&time| key_frame_index(&mode, &[0.1; 3], time)).collect()
+        };
+
+        assert_eq!(frames(&[0.05, 0.15]), vec![0, 1]);
+        assert_eq!(frames(&[0.25, 0.4, 0.5]), vec![2, 2, 2]);
+        assert_eq!(frames(&[0.6]), vec![1]);
+        assert_eq!(frames(&[0.7, 0.85]), vec![0, 0]);
+        assert_eq!(frames(&[0.95, 1.05, 1.15]), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn loop_time_wraps_at_the_cycle_length() {
+        assert!((loop_time(&PlayMode::Loop, &[0.1; 4], 0.45) - 0.05).abs() < 1e-5);
+        assert!((loop_time(&PlayMode::LoopPingPong, &[0.1; 4], 0.45) - 0.45).abs() < 1e-5);
+        assert!((loop_time(&PlayMode::LoopPingPong, &[0.1; 4], 0.65) - 0.05).abs() < 1e-5);
+        let hold = PlayMode::LoopPingPongHold { hold: 0.25 };
+        assert!((loop_time(&hold, &[0.1; 3], 0.95) - 0.05).abs() < 1e-5);
+        assert_eq!(loop_time(&PlayMode::Normal, &[0.1; 4], 2.0), 0.4);
+        assert_eq!(loop_time(&PlayMode::Reversed, &[0.1; 4], -1.0), 0.0);
+    }
+
+    #[test]
+    fn frames_entered_follows_loop_wraps() {
+        assert_eq!(frames_entered(&PlayMode::Loop, &[0.1; 3], 0.05, 0.45), vec![1, 2, 0, 1]);
+        assert_eq!(frames_entered(&PlayMode::Loop, &[0.1; 3], -1.0, 0.05), vec![0]);
+        assert_eq!(frames_entered(&PlayMode::Normal, &[0.1; 3], 0.15, 5.0), vec![2]);
+        assert_eq!(frames_entered(&PlayMode::Reversed, &[0.1; 3], 0.05, 0.15), vec![1]);
+    }
+
+    #[test]
+    fn frames_entered_follows_ping_pong() {
+        assert_eq!(frames_entered(&PlayMode::LoopPingPong, &[0.1; 3], 0.05, 0.45), vec![1, 2, 1, 0]);
+        let hold = PlayMode::LoopPingPongHold { hold: 0.25 };
+        assert_eq!(frames_entered(&hold, &[0.1; 3], 0.05, 0.5), vec![1, 2]);
+        assert_eq!(frames_entered(&hold, &[0.1; 3], 0.5, 1.05), vec![1, 0, 1]);
+    }
+
+    #[test]
+    fn ping_pong_hold_without_hold_matches_ping_pong() {
+        let hold = PlayMode::LoopPingPongHold { hold: 0.0 };
+        for step in 0..40 {
+            let time = step as f32 * 0.1 + 0.05;
+            assert_eq!(key_frame_index(&hold, &[0.1; 4], time), key_frame_index(&PlayMode::LoopPingPong, &[0.1; 4], time));
+        }
+    }
+
+    #[test]
+    fn flipbook_frames_follow_fps_and_looping() {
+        assert_eq!(flipbook_frame(10.0, 4, true, 0.05), 0);
+        assert_eq!(flipbook_frame(10.0, 4, true, 0.25), 2);
+        assert_eq!(flipbook_frame(10.0, 4, true, 0.45), 0);
+        assert_eq!(flipbook_frame(10.0, 4, false, 0.45), 3);
+        assert_eq!(flipbook_frame(10.0, 4, false, -1.0), 0);
+    }
+
+    #[test]
+    fn flipbook_cells_start_at_the_top_left() {
+        assert_eq!(flipbook_cell(0, 4, 2, (64, 32)), ((0, 16), (16, 16)));
+        assert_eq!(flipbook_cell(5, 4, 2, (64, 32)), ((16, 0), (16, 16)));
+    }
+
+    #[test]
+    fn frame_durations_are_accumulated() {
+        let durations = [0.1, 0.3, 0.2];
+        let frames = |mode: PlayMode, times: &[f32]| -> Vec<u32> {
+            times.iter().map(|&time| key_frame_index(&mode, &durations, time)).collect()
+        };
+
+        assert_eq!(frames(PlayMode::Normal, &[0.05, 0.15, 0.35, 0.45, 5.0]), vec![0, 1, 1, 2, 2]);
+        assert_eq!(frames(PlayMode::Reversed, &[0.1, 0.3, 0.55, 5.0]), vec![2, 1, 0, 0]);
+        assert_eq!(frames(PlayMode::Loop, &[0.65, 0.75]), vec![0, 1]);
+        assert_eq!(frames(PlayMode::LoopReversed, &[0.65, 0.85]), vec![2, 1]);
+        // The reverse pass shows frame 1 for its 0.3 seconds again, so a cycle takes 0.9 seconds.
+        assert_eq!(frames(PlayMode::LoopPingPong, &[0.05, 0.35, 0.5, 0.65, 0.85, 0.95]), vec![0, 1, 2, 1, 1, 0]);
+        assert!((cycle_duration(&PlayMode::LoopPingPong, &durations) - 0.9).abs() < 1e-5);
+    }
+
+    #[test]
+    fn finished_only_past_the_final_frame_boundary() {
+        let durations = [0.1; 3];
+        let (before, after) = (0.3 - 1e-4, 0.3 + 1e-4);
+        let modes = [
+            (PlayMode::Normal, 2, 2, false, true),
+            (PlayMode::Reversed, 0, 0, false, true),
+            (PlayMode::Loop, 2, 0, false, false),
+            (PlayMode::LoopReversed, 0, 2, false, false),
+            (PlayMode::LoopPingPong, 2, 1, false, false),
+            (PlayMode::LoopPingPongHold { hold: 0.25 }, 2, 2, false, false),
+        ];
+
+        for (mode, frame_before, frame_after, finished_before, finished_after) in modes.iter() {
+            assert_eq!(key_frame_index(mode, &durations, before), *frame_before);
+            assert_eq!(key_frame_index(mode, &durations, after), *frame_after);
+            assert_eq!(is_finished(mode, 0.3, before), *finished_before);
+            assert_eq!(is_finished(mode, 0.3, after), *finished_after);
+        }
+    }
+
+    #[test]
+    fn reversed_holds_the_first_frame_long_after_the_end() {
+        let durations = [0.1; 4];
+        assert_eq!(key_frame_index(&PlayMode::Reversed, &durations, 0.45), 0);
+        assert_eq!(key_frame_index(&PlayMode::Reversed, &durations, 1000.0), 0);
+        assert_eq!(key_frame_index(&PlayMode::Reversed, &durations, f32::MAX), 0);
+    }
+}
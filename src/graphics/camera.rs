@@ -0,0 +1,112 @@
+/// A 2D camera looking at `position`, which ends up in the center of the viewport. A zoom above
+/// `1.0` shows more of the world, below `1.0` less. Changes take effect on the next `update`.
+#[derive(Clone, Debug)]
+pub struct OrthographicCamera {
+    position: glm::Vec2,
+    zoom: f32,
+    viewport_size: (f32, f32),
+    combined: glm::Mat4,
+}
+
+impl OrthographicCamera {
+    /// Creates a camera centered on the viewport, so that its `combined` matrix initially equals
+    /// `glm::ortho(0.0, width, 0.0, height, -1.0, 1.0)`, the usual pixel projection.
+    pub fn new(viewport_width: f32, viewport_height: f32) -> Self {
+        let mut camera = OrthographicCamera {
+            position: glm::vec2(viewport_width / 2.0, viewport_height / 2.0),
+            zoom: 1.0,
+            viewport_size: (viewport_width, viewport_height),
+            combined: glm::identity(),
+        };
+        camera.update();
+        camera
+    }
+
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.position += glm::vec2(dx, dy);
+    }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.position = glm::vec2(x, y);
+    }
+
+    pub fn position(&self) -> glm::Vec2 {
+        self.position
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom;
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Usually the window size, call it on `AppGDX::resize`.
+    pub fn set_viewport_size(&mut self, width: f32, height: f32) {
+        self.viewport_size = (width, height);
+    }
+
+    pub fn viewport_size(&self) -> (f32, f32) {
+        self.viewport_size
+    }
+
+    /// Recomputes the `combined` matrix after changing the position, zoom or viewport size.
+    pub fn update(&mut self) {
+        let half_width = self.viewport_size.0 * self.zoom / 2.0;
+        let half_height = self.viewport_size.1 * self.zoom / 2.0;
+        self.combined = glm::ortho(
+            self.position.x - half_width,
+            self.position.x + half_width,
+            self.position.y - half_height,
+            self.position.y + half_height,
+            -1.0,
+            1.0,
+        );
+    }
+
+    /// The projection-view matrix as of the last `update`, for `SpriteRenderer::set_projection_matrix`
+    /// and the other renderers.
+    pub fn combined(&self) -> glm::Mat4 {
+        self.combined
+    }
+
+    /// Converts a position in viewport pixels with the origin at the top-left, like
+    /// `Input::mouse_pos`, into world coordinates. Uses the current position and zoom even
+    /// before `update`.
+    pub fn unproject(&self, screen_x: f32, screen_y: f32) -> (f32, f32) {
+        let (width, height) = self.viewport_size;
+        (
+            self.position.x + (screen_x - width / 2.0) * self.zoom,
+            self.position.y + (height / 2.0 - screen_y) * self.zoom,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn new_camera_matches_pixel_projection() {
+        let camera = OrthographicCamera::new(800.0, 600.0);
+        assert_eq!(camera.combined(), glm::ortho(0.0, 800.0, 0.0, 600.0, -1.0, 1.0));
+        assert_eq!(camera.unproject(0.0, 0.0), (0.0, 600.0));
+        assert_eq!(camera.unproject(800.0, 600.0), (800.0, 0.0));
+    }
+
+    #[test]
+    fn unproject_accounts_for_pan_and_zoom() {
+        let mut camera = OrthographicCamera::new(800.0, 600.0);
+        camera.set_position(100.0, 50.0);
+        camera.translate(10.0, -10.0);
+        camera.set_zoom(2.0);
+        camera.update();
+
+        assert_eq!(camera.unproject(400.0, 300.0), (110.0, 40.0));
+        assert_eq!(camera.unproject(0.0, 0.0), (110.0 - 800.0, 40.0 + 600.0));
+
+        let world = camera.combined() * glm::vec4(110.0 + 800.0, 40.0 - 600.0, 0.0, 1.0);
+        assert!((world.x - 1.0).abs() < 1e-5 && (world.y + 1.0).abs() < 1e-5);
+    }
+}
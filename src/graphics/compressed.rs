@@ -0,0 +1,277 @@
+//! Loading of GPU-compressed textures from DDS and KTX (version 1) files.
+//!
+//! Only the S3TC/BC formats are supported: DXT1 (BC1, with or without alpha), DXT3 (BC2) and DXT5
+//! (BC3). Just the base mip level is uploaded. The compressed blocks are handed to the GPU as they
+//! are, so the texture cannot be packed into sprite batches. Draw it with
+//! `SpriteRenderer::draw_compressed`, or sample the `CompressedTexture2d` in a custom shader.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use glium::texture::{CompressedFormat, CompressedMipmapsOption, CompressedTexture2d};
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+const DDS_HEADER_SIZE: usize = 128;
+
+const KTX_IDENTIFIER: [u8; 12] = [0xAB, 0x4B, 0x54, 0x58, 0x20, 0x31, 0x31, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A];
+const KTX_HEADER_SIZE: usize = 64;
+const KTX_ENDIANNESS: u32 = 0x0403_0201;
+
+const GL_COMPRESSED_RGB_S3TC_DXT1: u32 = 0x83F0;
+const GL_COMPRESSED_RGBA_S3TC_DXT1: u32 = 0x83F1;
+const GL_COMPRESSED_RGBA_S3TC_DXT3: u32 = 0x83F2;
+const GL_COMPRESSED_RGBA_S3TC_DXT5: u32 = 0x83F3;
+
+#[derive(Debug)]
+pub enum CompressedTextureError {
+    Io(io::Error),
+    InvalidFile(&'static str),
+    UnsupportedFormat(String),
+    NotSupportedByGpu(CompressedFormat),
+    Creation(glium::texture::TextureCreationError),
+}
+
+impl fmt::Display for CompressedTextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompressedTextureError::Io(e) => write!(f, "could not read texture file: {}", e),
+            CompressedTextureError::InvalidFile(reason) => write!(f, "invalid texture file: {}", reason),
+            CompressedTextureError::UnsupportedFormat(format) =>
+                write!(f, "unsupported compressed format {}", format),
+            CompressedTextureError::NotSupportedByGpu(format) =>
+                write!(f, "compressed format {:?} is not supported by the GPU", format),
+            CompressedTextureError::Creation(e) => write!(f, "could not create texture: {:?}", e),
+        }
+    }
+}
+
+impl Error for CompressedTextureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CompressedTextureError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CompressedTextureError {
+    fn from(e: io::Error) -> Self {
+        CompressedTextureError::Io(e)
+    }
+}
+
+impl From<glium::texture::TextureCreationError> for CompressedTextureError {
+    fn from(e: glium::texture::TextureCreationError) -> Self {
+        CompressedTextureError::Creation(e)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct CompressedImage<'a> {
+    format: CompressedFormat,
+    width: u32,
+    height: u32,
+    data: &'a [u8],
+}
+
+/// Loads a DDS or KTX file, detected from its header, as a compressed texture.
+pub fn load_compressed_texture<F, P>(display: &F, path: P) -> Result<CompressedTexture2d, CompressedTextureError>
+where
+    F: glium::backend::Facade,
+    P: AsRef<Path>,
+{
+    let bytes = fs::read(path)?;
+    let image = parse_compressed(&bytes)?;
+    if !image.format.is_supported(display.get_context()) {
+        return Err(CompressedTextureError::NotSupportedByGpu(image.format));
+    }
+
+    Ok(CompressedTexture2d::with_compressed_data(
+        display,
+        image.data,
+        image.width,
+        image.height,
+        image.format,
+        CompressedMipmapsOption::NoMipmap,
+    )?)
+}
+
+fn parse_compressed(bytes: &[u8]) -> Result<CompressedImage, CompressedTextureError> {
+    if bytes.starts_with(DDS_MAGIC) {
+        parse_dds(bytes)
+    } else if bytes.starts_with(&KTX_IDENTIFIER) {
+        parse_ktx(bytes)
+    } else {
+        Err(CompressedTextureError::InvalidFile("neither a DDS nor a KTX file"))
+    }
+}
+
+fn parse_dds(bytes: &[u8]) -> Result<CompressedImage, CompressedTextureError> {
+    if bytes.len() < DDS_HEADER_SIZE {
+        return Err(CompressedTextureError::InvalidFile("truncated DDS header"));
+    }
+
+    let height = LittleEndian::read_u32(&bytes[12..16]);
+    let width = LittleEndian::read_u32(&bytes[16..20]);
+    let format = match &bytes[84..88] {
+        b"DXT1" => CompressedFormat::S3tcDxt1Alpha,
+        b"DXT3" => CompressedFormat::S3tcDxt3Alpha,
+        b"DXT5" => CompressedFormat::S3tcDxt5Alpha,
+        four_cc => return Err(CompressedTextureError::UnsupportedFormat(
+            format!("DDS {:?}", String::from_utf8_lossy(four_cc)))),
+    };
+
+    let data = base_level(&bytes[DDS_HEADER_SIZE..], format, width, height)?;
+    Ok(CompressedImage { format, width, height, data })
+}
+
+fn parse_ktx(bytes: &[u8]) -> Result<CompressedImage, CompressedTextureError> {
+    if bytes.len() < KTX_HEADER_SIZE {
+        return Err(CompressedTextureError::InvalidFile("truncated KTX header"));
+    }
+
+    let read_u32 = if LittleEndian::read_u32(&bytes[12..16]) == KTX_ENDIANNESS {
+        LittleEndian::read_u32
+    } else if BigEndian::read_u32(&bytes[12..16]) == KTX_ENDIANNESS {
+        BigEndian::read_u32
+    } else {
+        return Err(CompressedTextureError::InvalidFile("invalid KTX endianness"));
+    };
+    let field = |index: usize| read_u32(&bytes[12 + index * 4..16 + index * 4]);
+
+    let format = match field(4) {
+        GL_COMPRESSED_RGB_S3TC_DXT1 => CompressedFormat::S3tcDxt1NoAlpha,
+        GL_COMPRESSED_RGBA_S3TC_DXT1 => CompressedFormat::S3tcDxt1Alpha,
+        GL_COMPRESSED_RGBA_S3TC_DXT3 => CompressedFormat::S3tcDxt3Alpha,
+        GL_COMPRESSED_RGBA_S3TC_DXT5 => CompressedFormat::S3tcDxt5Alpha,
+        internal_format => return Err(CompressedTextureError::UnsupportedFormat(
+            format!("KTX glInternalFormat {:#x}", internal_format))),
+    };
+    let width = field(6);
+    let height = field(7);
+    let key_value_bytes = field(12) as usize;
+
+    let image_size_offset = KTX_HEADER_SIZE + key_value_bytes;
+    if bytes.len() < image_size_offset + 4 {
+        return Err(CompressedTextureError::InvalidFile("truncated KTX image data"));
+    }
+    let image_size = read_u32(&bytes[image_size_offset..image_size_offset + 4]) as usize;
+    let image_data = &bytes[image_size_offset + 4..];
+    if image_data.len() < image_size {
+        return Err(CompressedTextureError::InvalidFile("truncated KTX image data"));
+    }
+
+    let data = base_level(&image_data[..image_size], format, width, height)?;
+    Ok(CompressedImage { format, width, height, data })
+}
+
+fn base_level(data: &[u8], format: CompressedFormat, width: u32,
+              height: u32) -> Result<&[u8], CompressedTextureError> {
+    let block_size = match format {
+        CompressedFormat::S3tcDxt1NoAlpha | CompressedFormat::S3tcDxt1Alpha => 8,
+        _ => 16,
+    };
+    let blocks_wide = ((width as usize + 3) / 4).max(1);
+    let blocks_high = ((height as usize + 3) / 4).max(1);
+    let size = blocks_wide * blocks_high * block_size;
+
+    if data.len() < size {
+        return Err(CompressedTextureError::InvalidFile("not enough data for the base mip level"));
+    }
+    Ok(&data[..size])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dds(four_cc: &[u8; 4], width: u32, height: u32, data_len: usize) -> Vec<u8> {
+        let mut bytes = vec![0; DDS_HEADER_SIZE + data_len];
+        bytes[..4].copy_from_slice(DDS_MAGIC);
+        LittleEndian::write_u32(&mut bytes[4..8], 124);
+        LittleEndian::write_u32(&mut bytes[12..16], height);
+        LittleEndian::write_u32(&mut bytes[16..20], width);
+        bytes[84..88].copy_from_slice(four_cc);
+        bytes
+    }
+
+    fn ktx(internal_format: u32, width: u32, height: u32, data_len: usize) -> Vec<u8> {
+        let mut bytes = vec![0; KTX_HEADER_SIZE + 4 + data_len];
+        bytes[..12].copy_from_slice(&KTX_IDENTIFIER);
+        BigEndian::write_u32(&mut bytes[12..16], KTX_ENDIANNESS);
+        BigEndian::write_u32(&mut bytes[28..32], internal_format);
+        BigEndian::write_u32(&mut bytes[36..40], width);
+        BigEndian::write_u32(&mut bytes[40..44], height);
+        BigEndian::write_u32(&mut bytes[64..68], data_len as u32);
+        bytes
+    }
+
+    #[test]
+    fn dds_base_level_is_sized_in_blocks() {
+        let bytes = dds(b"DXT5", 8, 6, 200);
+        let image = parse_compressed(&bytes).unwrap();
+        assert_eq!(image.format, CompressedFormat::S3tcDxt5Alpha);
+        assert_eq!((image.width, image.height), (8, 6));
+        assert_eq!(image.data.len(), 2 * 2 * 16);
+
+        let bytes = dds(b"DXT1", 2, 2, 8);
+        assert_eq!(parse_compressed(&bytes).unwrap().data.len(), 8);
+    }
+
+    #[test]
+    fn ktx_header_is_read_in_its_endianness() {
+        let bytes = ktx(GL_COMPRESSED_RGB_S3TC_DXT1, 4, 4, 8);
+        let image = parse_compressed(&bytes).unwrap();
+        assert_eq!(image.format, CompressedFormat::S3tcDxt1NoAlpha);
+        assert_eq!((image.width, image.height), (4, 4));
+        assert_eq!(image.data.len(), 8);
+    }
+
+    #[test]
+    fn unsupported_and_truncated_files_are_rejected() {
+        match parse_compressed(&dds(b"ATI2", 4, 4, 16)) {
+            Err(CompressedTextureError::UnsupportedFormat(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match parse_compressed(&dds(b"DXT5", 8, 8, 16)) {
+            Err(CompressedTextureError::InvalidFile(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(parse_compressed(b"not a texture").is_err());
+    }
+
+    #[test]
+    #[ignore]
+    fn compressed_texture_is_drawn_upright() {
+        use glium::Surface;
+
+        use crate::graphics::Rect;
+        use crate::graphics::render_target::RenderTarget;
+        use crate::graphics::sprite::{SpriteDrawParams, SpriteRenderer};
+        use crate::graphics::test_support::hidden_display;
+
+        let (_sdl_context, display) = hidden_display();
+
+        // Two solid DXT1 blocks stored top-down: red above blue.
+        let red = [0x00, 0xF8, 0x00, 0xF8, 0, 0, 0, 0];
+        let blue = [0x1F, 0x00, 0x1F, 0x00, 0, 0, 0, 0];
+        let texture = CompressedTexture2d::with_compressed_data(
+            &display, &[red, blue].concat(), 4, 8, CompressedFormat::S3tcDxt1NoAlpha,
+            CompressedMipmapsOption::NoMipmap).unwrap();
+
+        let target = RenderTarget::new(&display, 4, 8);
+        let renderer = SpriteRenderer::new(&display, target.projection());
+        let mut surface = target.surface();
+        surface.clear_color(0.0, 0.0, 0.0, 0.0);
+        renderer.draw_compressed(&texture, Rect::new(0.0, 0.0, 4.0, 8.0), [1.0; 4],
+                                 SpriteDrawParams::new(), &mut surface);
+
+        let pixels: Vec<Vec<(u8, u8, u8, u8)>> = target.texture().read();
+        assert_eq!(pixels[7][0], (255, 0, 0, 255));
+        assert_eq!(pixels[0][0], (0, 0, 255, 255));
+    }
+}
@@ -0,0 +1,166 @@
+use glium::{DrawError, Surface, uniform};
+pub use glium::index::PrimitiveType;
+use glium::uniforms::Sampler;
+
+use crate::graphics::sprite::{SpriteDrawParams, VertexData};
+use crate::graphics::texture::TextureRegion;
+
+const VERTEX_SHADER_SRC: &str = include_str!("shaders/sprite.vs.glsl");
+const FRAGMENT_SHADER_SRC: &str = include_str!("shaders/sprite.fs.glsl");
+
+/// Draws arbitrary indexed geometry such as triangle strips for trails and ribbons, using the
+/// sprite shader. Vertices are `VertexData` like in a `SpriteBatch`: a position in world units,
+/// texture coordinates in 0..1 with the origin at the bottom-left of the texture, and a color
+/// that is multiplied with the sampled texel.
+pub struct MeshRenderer {
+    projection_matrix: glm::Mat4,
+    shader: glium::Program,
+}
+
+impl MeshRenderer {
+    pub fn new<F: glium::backend::Facade>(display: &F, projection: glm::Mat4) -> Self {
+        let program_creation_input = glium::program::ProgramCreationInput::SourceCode {
+            vertex_shader: VERTEX_SHADER_SRC,
+            fragment_shader: FRAGMENT_SHADER_SRC,
+            geometry_shader: None,
+            tessellation_control_shader: None,
+            tessellation_evaluation_shader: None,
+            transform_feedback_varyings: None,
+            outputs_srgb: true,
+            uses_point_size: false,
+        };
+        let shader = glium::Program::new(display, program_creation_input)
+            .expect("Could not create MeshRenderer shader program.");
+
+        Self::with_shader(display, shader, projection)
+    }
+
+    pub fn with_shader<F: glium::backend::Facade>(_display: &F, shader: glium::Program,
+                                                  projection: glm::Mat4) -> Self {
+        MeshRenderer {
+            projection_matrix: projection,
+            shader,
+        }
+    }
+
+    pub fn draw<F, S>(&self, display: &F, texture: &glium::Texture2d, vertices: &[VertexData], indices: &[u16],
+                      primitive_type: PrimitiveType, draw_params: SpriteDrawParams,
+                      target: &mut S) -> Result<(), DrawError>
+    where
+        F: glium::backend::Facade,
+        S: Surface,
+    {
+        if vertices.is_empty() || indices.is_empty() {
+            return Ok(());
+        }
+
+        let vertex_buffer = glium::VertexBuffer::immutable(display, vertices)
+            .expect("Could not create MeshRenderer vertex buffer.");
+        let index_buffer = glium::IndexBuffer::immutable(display, primitive_type, indices)
+            .expect("Could not create MeshRenderer index buffer.");
+
+        let sampler: Sampler<glium::Texture2d> = glium::uniforms::Sampler(
+            texture,
+            draw_params.sampler_behavior,
+        );
+        let uniforms = uniform! {
+            image: sampler,
+            projectionView: *self.projection_matrix.as_ref(),
+            ignoreVertexColor: draw_params.ignore_vertex_color,
+        };
+        let params = glium::DrawParameters {
+            blend: draw_params.blend(),
+            .. Default::default()
+        };
+
+        target.draw(&vertex_buffer, &index_buffer, &self.shader, &uniforms, &params)
+    }
+
+    /// Draws the part of a circle swept from `start_deg` by `sweep_deg`, e.g. for cooldown
+    /// indicators. Angles are counterclockwise from the positive x axis, so a negative sweep wipes
+    /// clockwise; sweeps beyond a full turn are clamped to it. `region` is mapped onto the square
+    /// around the circle, so a pie slice shows the matching part of the texture. The arc is
+    /// approximated by `segments` triangles for a full turn, proportionally fewer for less.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_radial_fill<F, S>(&self, display: &F, region: &TextureRegion, center: (f32, f32), radius: f32,
+                                  start_deg: f32, sweep_deg: f32, segments: u32, color: [f32; 4],
+                                  draw_params: SpriteDrawParams, target: &mut S) -> Result<(), DrawError>
+    where
+        F: glium::backend::Facade,
+        S: Surface,
+    {
+        let points = radial_fill_points(region.normalized_offset(), region.normalized_size(), center, radius,
+                                        start_deg, sweep_deg, segments);
+        let vertices: Vec<VertexData> = points.iter()
+            .map(|&(pos, tex_coords)| VertexData::new(pos, tex_coords, color))
+            .collect();
+        let indices: Vec<u16> = (1..points.len().saturating_sub(1) as u16)
+            .flat_map(|i| vec![0, i, i + 1])
+            .collect();
+
+        self.draw(display, region.texture(), &vertices, &indices, PrimitiveType::TrianglesList, draw_params, target)
+    }
+
+    pub fn set_projection_matrix(&mut self, projection: glm::Mat4) {
+        self.projection_matrix = projection;
+    }
+
+    pub fn get_projection_matrix(&self) -> glm::Mat4 {
+        self.projection_matrix
+    }
+}
+
+/// Positions and texture coordinates of a triangle fan: the center followed by the arc.
+fn radial_fill_points(tex_offset: glm::Vec2, tex_size: glm::Vec2, center: (f32, f32), radius: f32, start_deg: f32,
+                      sweep_deg: f32, segments: u32) -> Vec<([f32; 2], [f32; 2])> {
+    let sweep_deg = sweep_deg.max(-360.0).min(360.0);
+    if sweep_deg == 0.0 || radius <= 0.0 {
+        return Vec::new();
+    }
+    let arc_segments = ((segments.max(3) as f32 * sweep_deg.abs() / 360.0).ceil() as u32).max(1);
+
+    let point = |x: f32, y: f32| {
+        let tex_coords = [
+            tex_offset.x + (x - center.0 + radius) / (2.0 * radius) * tex_size.x,
+            tex_offset.y + (y - center.1 + radius) / (2.0 * radius) * tex_size.y,
+        ];
+        ([x, y], tex_coords)
+    };
+
+    let mut points = Vec::with_capacity(arc_segments as usize + 2);
+    points.push(point(center.0, center.1));
+    for i in 0..=arc_segments {
+        let angle = (start_deg + sweep_deg * i as f32 / arc_segments as f32).to_radians();
+        points.push(point(center.0 + radius * angle.cos(), center.1 + radius * angle.sin()));
+    }
+    points
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4
+    }
+
+    #[test]
+    fn full_sweep_closes_the_circle() {
+        let points = radial_fill_points(glm::vec2(0.5, 0.0), glm::vec2(0.5, 0.5), (10.0, 10.0), 2.0, 90.0, 360.0, 32);
+        assert_eq!(points.len(), 34);
+        assert_eq!(points[0], ([10.0, 10.0], [0.75, 0.25]));
+        assert!(close(points[1].0, [10.0, 12.0]));
+        assert!(close(points[1].1, [0.75, 0.5]));
+        assert!(close(points[33].0, points[1].0));
+    }
+
+    #[test]
+    fn negative_sweep_runs_clockwise() {
+        let counterclockwise = radial_fill_points(glm::vec2(0.0, 0.0), glm::vec2(1.0, 1.0), (0.0, 0.0), 1.0, 90.0, 90.0, 4);
+        let clockwise = radial_fill_points(glm::vec2(0.0, 0.0), glm::vec2(1.0, 1.0), (0.0, 0.0), 1.0, 90.0, -90.0, 4);
+        assert_eq!(counterclockwise.len(), 3);
+        assert!(close(counterclockwise[2].0, [-1.0, 0.0]));
+        assert!(close(clockwise[2].0, [1.0, 0.0]));
+        assert!(close(clockwise[2].1, [1.0, 0.5]));
+    }
+}
@@ -0,0 +1,125 @@
+use glium::{DrawError, Surface};
+
+use crate::graphics::Rect;
+use crate::graphics::sprite::SpriteBatch;
+use crate::graphics::texture::TextureRegion;
+
+/// A region split into a 3x3 grid by its insets, for panels and buttons that can be drawn at any
+/// size. The corners keep their size, the edges stretch along one axis and the center along both.
+#[derive(Clone)]
+pub struct NinePatch {
+    region: TextureRegion,
+    left: u32,
+    right: u32,
+    top: u32,
+    bottom: u32,
+    color: [f32; 4],
+}
+
+impl NinePatch {
+    /// Insets are given in texels of `region`, measured from its respective edge.
+    pub fn new(region: TextureRegion, left: u32, right: u32, top: u32, bottom: u32) -> Self {
+        let size = region.size();
+        assert!(left + right <= size.x && top + bottom <= size.y,
+                "NinePatch insets exceed the region size {}x{}!", size.x, size.y);
+
+        NinePatch {
+            region,
+            left,
+            right,
+            top,
+            bottom,
+            color: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+
+    pub fn region(&self) -> &TextureRegion {
+        &self.region
+    }
+
+    /// The insets as `(left, right, top, bottom)`.
+    pub fn insets(&self) -> (u32, u32, u32, u32) {
+        (self.left, self.right, self.top, self.bottom)
+    }
+
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    /// The smallest size at which the corners are drawn without shrinking.
+    pub fn min_size(&self) -> (f32, f32) {
+        ((self.left + self.right) as f32, (self.top + self.bottom) as f32)
+    }
+
+    /// Draws the patch with its bottom-left corner at `(x, y)`. If the size is smaller than
+    /// `min_size`, the corners shrink proportionally instead of overlapping.
+    pub fn draw<S: Surface>(&self, batch: &mut SpriteBatch<S>, x: f32, y: f32, width: f32,
+                            height: f32) -> Result<(), DrawError> {
+        let columns = segments(width.max(0.0), self.left as f32, self.right as f32);
+        let rows = segments(height.max(0.0), self.bottom as f32, self.top as f32);
+
+        let texture_size = self.region.texture_size();
+        let offset = self.region.normalized_offset();
+        let size = self.region.normalized_size();
+        let u = texture_splits(offset.x, size.x, self.left, self.right, texture_size.x);
+        let v = texture_splits(offset.y, size.y, self.bottom, self.top, texture_size.y);
+
+        let mut cell_y = y;
+        for row in 0..3 {
+            let mut cell_x = x;
+            for column in 0..3 {
+                if columns[column] > 0.0 && rows[row] > 0.0 {
+                    let rect = Rect::new(cell_x, cell_y, columns[column], rows[row]);
+                    batch.draw_quad(self.region.rc_texture(), rect, [u[column], v[row]],
+                                    [u[column + 1], v[row + 1]], self.color)?;
+                }
+                cell_x += columns[column];
+            }
+            cell_y += rows[row];
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits `total` into the sizes of the start inset, the stretched middle and the end inset. The
+/// insets shrink proportionally when they don't fit.
+fn segments(total: f32, start: f32, end: f32) -> [f32; 3] {
+    let insets = start + end;
+    if insets > total {
+        let scale = total / insets;
+        return [start * scale, 0.0, end * scale];
+    }
+    [start, total - insets, end]
+}
+
+fn texture_splits(offset: f32, size: f32, start: u32, end: u32, texture_size: u32) -> [f32; 4] {
+    let texture_size = texture_size as f32;
+    [offset, offset + start as f32 / texture_size, offset + size - end as f32 / texture_size, offset + size]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn middle_stretches_between_insets() {
+        assert_eq!(segments(100.0, 8.0, 12.0), [8.0, 80.0, 12.0]);
+        assert_eq!(segments(20.0, 8.0, 12.0), [8.0, 0.0, 12.0]);
+    }
+
+    #[test]
+    fn insets_shrink_instead_of_overlapping() {
+        assert_eq!(segments(10.0, 8.0, 12.0), [4.0, 0.0, 6.0]);
+        assert_eq!(segments(0.0, 8.0, 12.0), [0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn texture_splits_are_relative_to_the_region() {
+        assert_eq!(texture_splits(0.25, 0.5, 8, 16, 64), [0.25, 0.375, 0.5, 0.75]);
+    }
+}
@@ -0,0 +1,167 @@
+//! Pixel-perfect picking through an ID buffer.
+//!
+//! `PickingRenderer::pick` draws every pickable sprite into an offscreen texture with its id
+//! encoded as a color, then reads back the single pixel under the requested point. This costs an
+//! extra render pass over all pickable sprites plus a GPU readback, which stalls until the pass is
+//! done, so pick on clicks rather than every frame.
+
+use glium::{DrawError, Surface, uniform};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+
+use crate::graphics::sprite::{Sprite, VertexData};
+use crate::graphics::texture::TextureRegionHolder;
+
+const VERTEX_SHADER_SRC: &str = include_str!("shaders/sprite.vs.glsl");
+const FRAGMENT_SHADER_SRC: &str = include_str!("shaders/picking.fs.glsl");
+
+const MAX_PICKING_ID: u32 = 0x00ff_fffe;
+
+pub struct PickingRenderer {
+    projection_matrix: glm::Mat4,
+    shader: glium::Program,
+    id_texture: Option<glium::Texture2d>,
+    alpha_threshold: f32,
+}
+
+impl PickingRenderer {
+    pub fn new<F: glium::backend::Facade>(display: &F, projection: glm::Mat4) -> Self {
+        let program_creation_input = glium::program::ProgramCreationInput::SourceCode {
+            vertex_shader: VERTEX_SHADER_SRC,
+            fragment_shader: FRAGMENT_SHADER_SRC,
+            geometry_shader: None,
+            tessellation_control_shader: None,
+            tessellation_evaluation_shader: None,
+            transform_feedback_varyings: None,
+            outputs_srgb: true,
+            uses_point_size: false,
+        };
+        let shader = glium::Program::new(display, program_creation_input)
+            .expect("Could not create PickingRenderer shader program.");
+
+        PickingRenderer {
+            projection_matrix: projection,
+            shader,
+            id_texture: None,
+            alpha_threshold: 0.0,
+        }
+    }
+
+    /// Texels with an alpha at or below the threshold are not pickable. Defaults to `0.0`.
+    pub fn set_alpha_threshold(&mut self, alpha_threshold: f32) {
+        self.alpha_threshold = alpha_threshold;
+    }
+
+    pub fn alpha_threshold(&self) -> f32 {
+        self.alpha_threshold
+    }
+
+    /// Returns the id of the topmost sprite covering `point`, given in window pixels with the origin
+    /// at the top-left like `Input::mouse_pos`. Later sprites in `sprites` are drawn on top. Ids
+    /// have to be below `0xffffff`.
+    pub fn pick<F>(&mut self, display: &F, sprites: &[(u32, &Sprite)],
+                   point: (u32, u32)) -> Result<Option<u32>, DrawError>
+    where
+        F: glium::backend::Facade,
+    {
+        let (width, height) = display.get_context().get_framebuffer_dimensions();
+        if point.0 >= width || point.1 >= height {
+            return Ok(None);
+        }
+
+        let needs_texture = self.id_texture.as_ref()
+            .map_or(true, |texture| texture.dimensions() != (width, height));
+        if needs_texture {
+            self.id_texture = Some(glium::Texture2d::empty_with_format(
+                display,
+                glium::texture::UncompressedFloatFormat::U8U8U8U8,
+                glium::texture::MipmapsOption::NoMipmap,
+                width,
+                height,
+            ).expect("Could not create picking texture."));
+        }
+        let id_texture = self.id_texture.as_ref().unwrap();
+
+        let mut framebuffer = glium::framebuffer::SimpleFrameBuffer::new(display, id_texture)
+            .expect("Could not create picking framebuffer.");
+        framebuffer.clear_color(0.0, 0.0, 0.0, 0.0);
+
+        let indices = glium::index::NoIndices(glium::index::PrimitiveType::TriangleFan);
+        for &(id, sprite) in sprites {
+            let mut vertices = sprite.get_vertex_data();
+            let color = encode_id(id);
+            for vertex in vertices.iter_mut() {
+                vertex.set_color(color);
+            }
+            let vertex_buffer = glium::VertexBuffer::immutable(display, &vertices)
+                .expect("Could not create PickingRenderer vertex buffer.");
+
+            let sampler = sprite.texture().sampled()
+                .minify_filter(MinifySamplerFilter::Nearest)
+                .magnify_filter(MagnifySamplerFilter::Nearest);
+            let uniforms = uniform! {
+                image: sampler,
+                projectionView: *self.projection_matrix.as_ref(),
+                alphaThreshold: self.alpha_threshold,
+            };
+            framebuffer.draw(&vertex_buffer, &indices, &self.shader, &uniforms, &Default::default())?;
+        }
+
+        let rect = glium::Rect {
+            left: point.0,
+            bottom: height - 1 - point.1,
+            width: 1,
+            height: 1,
+        };
+        let pixels: Vec<Vec<(u8, u8, u8, u8)>> = id_texture.main_level().first_layer()
+            .into_image(None)
+            .expect("Could not access picking texture.")
+            .raw_read(&rect);
+
+        Ok(pixels.first()
+            .and_then(|row| row.first())
+            .and_then(|&(r, g, b, _)| decode_id([r, g, b])))
+    }
+
+    pub fn set_projection_matrix(&mut self, projection: glm::Mat4) {
+        self.projection_matrix = projection;
+    }
+
+    pub fn get_projection_matrix(&self) -> glm::Mat4 {
+        self.projection_matrix
+    }
+}
+
+fn encode_id(id: u32) -> [f32; 4] {
+    assert!(id <= MAX_PICKING_ID, "Picking id {} is too large!", id);
+    let value = id + 1;
+    [
+        ((value >> 16) & 0xff) as f32 / 255.0,
+        ((value >> 8) & 0xff) as f32 / 255.0,
+        (value & 0xff) as f32 / 255.0,
+        1.0,
+    ]
+}
+
+fn decode_id(rgb: [u8; 3]) -> Option<u32> {
+    let value = ((rgb[0] as u32) << 16) | ((rgb[1] as u32) << 8) | rgb[2] as u32;
+    value.checked_sub(1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ids_survive_a_round_trip_through_8_bit_channels() {
+        for &id in &[0, 1, 255, 256, 65_535, 123_456, MAX_PICKING_ID] {
+            let color = encode_id(id);
+            let rgb = [
+                (color[0] * 255.0).round() as u8,
+                (color[1] * 255.0).round() as u8,
+                (color[2] * 255.0).round() as u8,
+            ];
+            assert_eq!(decode_id(rgb), Some(id));
+        }
+        assert_eq!(decode_id([0, 0, 0]), None);
+    }
+}
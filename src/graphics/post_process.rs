@@ -0,0 +1,119 @@
+//! Fullscreen color adjustments for a frame rendered into a `RenderTarget`.
+//!
+//! The built-in shader (`shaders/post_process.fs.glsl`) applies, per channel, first contrast around
+//! mid-gray and brightness, `(c - 0.5) * contrast + 0.5 + brightness`, then clamps to 0..1 and
+//! applies gamma as `c^(1 / gamma)`. The default `PostProcessParams` leave the image unchanged.
+
+use glium::{DrawError, Surface, uniform};
+use glium::uniforms::{Sampler, SamplerBehavior};
+
+use crate::graphics::sprite::VertexData;
+use crate::graphics::texture::TextureRegion;
+
+const VERTEX_SHADER_SRC: &str = include_str!("shaders/sprite.vs.glsl");
+const FRAGMENT_SHADER_SRC: &str = include_str!("shaders/post_process.fs.glsl");
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostProcessParams {
+    pub brightness: f32,
+    pub contrast: f32,
+    pub gamma: f32,
+    pub sampler_behavior: SamplerBehavior,
+}
+
+impl Default for PostProcessParams {
+    fn default() -> Self {
+        PostProcessParams {
+            brightness: 0.0,
+            contrast: 1.0,
+            gamma: 1.0,
+            sampler_behavior: Default::default(),
+        }
+    }
+}
+
+impl PostProcessParams {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Offset added to every channel. Defaults to `0.0`.
+    pub fn brightness(mut self, brightness: f32) -> Self {
+        self.brightness = brightness;
+        self
+    }
+
+    /// Factor for the distance from mid-gray. Defaults to `1.0`.
+    pub fn contrast(mut self, contrast: f32) -> Self {
+        self.contrast = contrast;
+        self
+    }
+
+    /// Values above `1.0` brighten the midtones, values below darken them. Defaults to `1.0`.
+    pub fn gamma(mut self, gamma: f32) -> Self {
+        self.gamma = gamma;
+        self
+    }
+}
+
+pub struct PostProcess {
+    shader: glium::Program,
+    vertex_buffer: glium::VertexBuffer<VertexData>,
+    index_buffer: glium::IndexBuffer<u16>,
+}
+
+impl PostProcess {
+    pub fn new<F: glium::backend::Facade>(display: &F) -> Self {
+        let program_creation_input = glium::program::ProgramCreationInput::SourceCode {
+            vertex_shader: VERTEX_SHADER_SRC,
+            fragment_shader: FRAGMENT_SHADER_SRC,
+            geometry_shader: None,
+            tessellation_control_shader: None,
+            tessellation_evaluation_shader: None,
+            transform_feedback_varyings: None,
+            outputs_srgb: true,
+            uses_point_size: false,
+        };
+        let shader = glium::Program::new(display, program_creation_input)
+            .expect("Could not create PostProcess shader program.");
+
+        let vertex_buffer = glium::VertexBuffer::empty_dynamic(display, 4)
+            .expect("Could not create PostProcess vertex buffer.");
+        let index_buffer = glium::IndexBuffer::immutable(
+            display,
+            glium::index::PrimitiveType::TrianglesList,
+            &[0, 1, 2, 0, 2, 3],
+        ).expect("Could not create PostProcess index buffer.");
+
+        PostProcess {
+            shader,
+            vertex_buffer,
+            index_buffer,
+        }
+    }
+
+    /// Draws `source`, usually `RenderTarget::region`, stretched over all of `target` with the
+    /// color adjustments of `params`.
+    pub fn apply<S: Surface>(&self, source: &TextureRegion, target: &mut S,
+                             params: PostProcessParams) -> Result<(), DrawError> {
+        let [tex_top_left, tex_top_right, tex_bottom_left, tex_bottom_right] = source.texture_coordinates();
+        let color = [1.0; 4];
+        self.vertex_buffer.write(&[
+            VertexData::new([-1.0, 1.0], tex_top_left, color),
+            VertexData::new([1.0, 1.0], tex_top_right, color),
+            VertexData::new([1.0, -1.0], tex_bottom_right, color),
+            VertexData::new([-1.0, -1.0], tex_bottom_left, color),
+        ]);
+
+        let identity: glm::Mat4 = glm::identity();
+        let uniforms = uniform! {
+            image: Sampler(source.texture(), params.sampler_behavior),
+            projectionView: *identity.as_ref(),
+            brightness: params.brightness,
+            contrast: params.contrast,
+            gamma: params.gamma,
+        };
+
+        target.draw(&self.vertex_buffer, &self.index_buffer, &self.shader, &uniforms, &Default::default())
+    }
+}
@@ -0,0 +1,85 @@
+//! Offscreen rendering into a texture, e.g. as the first pass of a post-processing effect.
+
+use std::rc::Rc;
+
+use glium::backend::{Context, Facade};
+use glium::framebuffer::SimpleFrameBuffer;
+
+use crate::graphics::texture::TextureRegion;
+
+pub struct RenderTarget {
+    context: Rc<Context>,
+    texture: Rc<glium::Texture2d>,
+}
+
+impl RenderTarget {
+    pub fn new<F: Facade>(display: &F, width: u32, height: u32) -> Self {
+        let texture = glium::Texture2d::empty_with_format(
+            display,
+            glium::texture::UncompressedFloatFormat::U8U8U8U8,
+            glium::texture::MipmapsOption::NoMipmap,
+            width,
+            height,
+        ).expect("Could not create render target texture.");
+
+        RenderTarget {
+            context: display.get_context().clone(),
+            texture: Rc::new(texture),
+        }
+    }
+
+    /// A surface drawing into the texture, to be passed to `SpriteRenderer::begin_batch` or any
+    /// other renderer. It is cheap to create, so get a new one for every pass.
+    pub fn surface(&self) -> SimpleFrameBuffer {
+        SimpleFrameBuffer::new(&self.context, &*self.texture)
+            .expect("Could not create render target framebuffer.")
+    }
+
+    /// Projection mapping the texture's pixels with (0, 0) at the bottom-left.
+    pub fn projection(&self) -> glm::Mat4 {
+        let (width, height) = self.size();
+        glm::ortho(0.0, width as f32, 0.0, height as f32, -1.0, 1.0)
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.texture.dimensions()
+    }
+
+    pub fn texture(&self) -> &Rc<glium::Texture2d> {
+        &self.texture
+    }
+
+    /// The whole texture as a region, e.g. to draw it fullscreen as a sprite.
+    pub fn region(&self) -> TextureRegion {
+        TextureRegion::new(self.texture.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use glium::Surface;
+
+    use super::*;
+    use crate::graphics::sprite::{Sprite, SpriteDrawParams, SpriteRenderer};
+    use crate::graphics::test_support::hidden_display;
+    use crate::graphics::texture::texture_from_pixels;
+
+    #[test]
+    #[ignore]
+    fn sprite_is_drawn_into_the_target() {
+        let (_sdl_context, display) = hidden_display();
+
+        let target = RenderTarget::new(&display, 4, 4);
+        let texture = texture_from_pixels(&display, &[255; 4 * 4 * 4], 4, 4).unwrap();
+        let mut sprite = Sprite::new(texture);
+        sprite.set_position(2.0, 2.0);
+
+        let renderer = SpriteRenderer::new(&display, target.projection());
+        let mut surface = target.surface();
+        surface.clear_color(0.0, 0.0, 0.0, 0.0);
+        renderer.draw(&sprite, SpriteDrawParams::new(), &mut surface);
+
+        let pixels: Vec<Vec<(u8, u8, u8, u8)>> = target.texture().read();
+        assert_ne!(pixels[1][1], (0, 0, 0, 0));
+    }
+}
@@ -0,0 +1,16 @@
+#version 330 core
+
+in vec2 texCoords;
+in vec4 spriteColor;
+
+out vec4 color;
+
+uniform sampler2D image;
+uniform float alphaThreshold;
+
+void main() {
+    if (texture(image, texCoords).a <= alphaThreshold) {
+        discard;
+    }
+    color = spriteColor;
+}
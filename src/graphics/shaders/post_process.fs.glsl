@@ -0,0 +1,20 @@
+#version 330 core
+
+in vec2 texCoords;
+
+out vec4 color;
+
+uniform sampler2D image;
+// Added to every channel, 0.0 leaves the image unchanged.
+uniform float brightness;
+// Scales the distance of every channel from mid-gray, 1.0 leaves the image unchanged.
+uniform float contrast;
+// Output is raised to 1 / gamma, 1.0 leaves the image unchanged and higher values brighten.
+uniform float gamma;
+
+void main() {
+    vec4 texel = texture(image, texCoords);
+    vec3 rgb = (texel.rgb - 0.5) * contrast + 0.5 + brightness;
+    rgb = pow(clamp(rgb, 0.0, 1.0), vec3(1.0 / gamma));
+    color = vec4(rgb, texel.a);
+}
@@ -2,11 +2,19 @@
 
 in vec2 texCoords;
 in vec4 spriteColor;
+in vec4 spriteTint;
 
 out vec4 color;
 
 uniform sampler2D image;
+uniform bool ignoreVertexColor;
 
 void main() {
-    color = vec4(spriteColor) * texture(image, texCoords);
+    if (ignoreVertexColor) {
+        color = texture(image, texCoords);
+    } else {
+        color = vec4(spriteColor) * texture(image, texCoords);
+    }
+    // The tint is applied on top of the color, a transparent tint leaves the sprite unchanged.
+    color.rgb = mix(color.rgb, spriteTint.rgb, spriteTint.a);
 }
@@ -3,9 +3,11 @@
 layout (location = 0) in vec2 pos;
 layout (location = 1) in vec2 tex_coords;
 layout (location = 2) in vec4 color;
+layout (location = 3) in vec4 tint;
 
 out vec2 texCoords;
 out vec4 spriteColor;
+out vec4 spriteTint;
 
 // The combined projection/view matrix.
 uniform mat4 projectionView;
@@ -13,5 +15,6 @@ uniform mat4 projectionView;
 void main() {
     texCoords = tex_coords;
     spriteColor = color;
+    spriteTint = tint;
     gl_Position = projectionView * vec4(pos, 0.0, 1.0);
 }
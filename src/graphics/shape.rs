@@ -4,6 +4,8 @@ use lyon_tessellation as tess;
 const VERTEX_SHADER_SRC: &str = include_str!("shaders/shape.vs.glsl");
 const FRAGMENT_SHADER_SRC: &str = include_str!("shaders/shape.fs.glsl");
 
+const DEFAULT_CIRCLE_SEGMENTS: u32 = 32;
+
 #[derive(Clone, Copy, Debug)]
 pub enum DrawMode {
     Fill,
@@ -76,10 +78,17 @@ impl tess::BasicVertexConstructor<VertexData> for VertexConstructor {
     }
 }
 
+/// Queues shapes and draws them all in one draw call. The `queue_*` methods take their color and
+/// transform per call; the immediate-mode style `begin`, `line`, `rect`, `circle` and `end` use the
+/// color set with `set_color` and tessellate circles with `circle_segments` segments.
 pub struct ShapeRenderer {
     projection_matrix: glm::Mat4,
     shader: glium::Program,
-    vertices: tess::VertexBuffers<VertexData, u16>,
+    vertices: tess::VertexBuffers<VertexData, u32>,
+    vertex_buffer: Option<glium::VertexBuffer<VertexData>>,
+    index_buffer: Option<glium::IndexBuffer<u32>>,
+    color: [f32; 4],
+    circle_segments: u32,
 }
 
 impl ShapeRenderer {
@@ -106,7 +115,97 @@ impl ShapeRenderer {
             projection_matrix: projection,
             shader,
             vertices: tess::VertexBuffers::new(),
+            vertex_buffer: None,
+            index_buffer: None,
+            color: [1.0, 1.0, 1.0, 1.0],
+            circle_segments: DEFAULT_CIRCLE_SEGMENTS,
+        }
+    }
+
+    /// Color of the shapes queued by `line`, `rect` and `circle`. Defaults to white.
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    /// Number of segments `circle` approximates a circle with. Defaults to 32, at least 3 are used.
+    pub fn set_circle_segments(&mut self, segments: u32) {
+        self.circle_segments = segments;
+    }
+
+    pub fn circle_segments(&self) -> u32 {
+        self.circle_segments
+    }
+
+    /// Starts a new frame of shapes, dropping anything queued but not drawn.
+    pub fn begin(&mut self) {
+        self.vertices.vertices.clear();
+        self.vertices.indices.clear();
+    }
+
+    /// Draws everything queued since `begin`, see `draw_queued`.
+    pub fn end<F, S>(&mut self, display: &F, target: &mut S)
+    where
+        F: glium::backend::Facade,
+        S: Surface,
+    {
+        self.draw_queued(display, target);
+    }
+
+    pub fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, width: f32) {
+        let direction = glm::vec2(x2 - x1, y2 - y1);
+        if direction == glm::vec2(0.0, 0.0) {
+            return;
         }
+        let normal = glm::normalize(&glm::vec2(-direction.y, direction.x)) * (width / 2.0);
+        self.push_shape(&[
+            [x1 + normal.x, y1 + normal.y],
+            [x2 + normal.x, y2 + normal.y],
+            [x2 - normal.x, y2 - normal.y],
+            [x1 - normal.x, y1 - normal.y],
+        ], &[0, 1, 2, 0, 2, 3]);
+    }
+
+    /// Unlike `queue_rect`, `x` and `y` are the bottom-left corner.
+    pub fn rect(&mut self, draw_mode: DrawMode, x: f32, y: f32, width: f32, height: f32) {
+        self.queue_rect(draw_mode, x + width / 2.0, y + height / 2.0, width, height, 0.0, self.color);
+    }
+
+    pub fn circle(&mut self, draw_mode: DrawMode, x: f32, y: f32, radius: f32) {
+        let segments = self.circle_segments.max(3);
+        match draw_mode {
+            DrawMode::Fill => {
+                let mut positions = vec![[x, y]];
+                positions.extend(circle_points(x, y, radius, segments));
+                let mut indices = Vec::with_capacity(segments as usize * 3);
+                for i in 0..segments {
+                    indices.extend_from_slice(&[0, i + 1, (i + 1) % segments + 1]);
+                }
+                self.push_shape(&positions, &indices);
+            }
+            DrawMode::Line(line_width) => {
+                let mut positions = circle_points(x, y, radius + line_width / 2.0, segments);
+                positions.extend(circle_points(x, y, (radius - line_width / 2.0).max(0.0), segments));
+                let mut indices = Vec::with_capacity(segments as usize * 6);
+                for i in 0..segments {
+                    let next = (i + 1) % segments;
+                    indices.extend_from_slice(&[i, next, segments + next, i, segments + next, segments + i]);
+                }
+                self.push_shape(&positions, &indices);
+            }
+        }
+    }
+
+    /// Queues a shape with `indices` into `positions`. Indices are 32 bits wide, so a frame can
+    /// queue more than 65536 vertices without them wrapping around.
+    fn push_shape(&mut self, positions: &[[f32; 2]], indices: &[u32]) {
+        let offset = self.vertices.vertices.len() as u32;
+        let color = self.color;
+        self.vertices.vertices.extend(positions.iter().map(|&pos| VertexData { pos, color }));
+        self.vertices.indices.extend(indices.iter().map(|index| index + offset));
     }
 
     pub fn queue_rect(&mut self, draw_mode: DrawMode, x: f32, y: f32, width: f32, height: f32, rotation: f32, color: [f32; 4]) {
@@ -164,13 +263,30 @@ impl ShapeRenderer {
         F: glium::backend::Facade,
         S: Surface,
     {
-        let vertex_buffer = glium::VertexBuffer::immutable(display, &self.vertices.vertices)
-            .expect("Could not create ShapeRenderer vertex buffer.");
-        let index_buffer = glium::IndexBuffer::immutable(
-            display,
-            glium::index::PrimitiveType::TrianglesList,
-            &self.vertices.indices,
-        ).expect("Could not create SpriteRenderer index buffer.");
+        let (vertex_count, index_count) = (self.vertices.vertices.len(), self.vertices.indices.len());
+        if index_count == 0 {
+            self.vertices.vertices.clear();
+            return;
+        }
+
+        if self.vertex_buffer.as_ref().map_or(true, |buffer| buffer.len() < vertex_count) {
+            self.vertex_buffer = Some(glium::VertexBuffer::empty_dynamic(display, vertex_count.next_power_of_two())
+                .expect("Could not create ShapeRenderer vertex buffer."));
+        }
+        if self.index_buffer.as_ref().map_or(true, |buffer| buffer.len() < index_count) {
+            self.index_buffer = Some(glium::IndexBuffer::empty_dynamic(
+                display,
+                glium::index::PrimitiveType::TrianglesList,
+                index_count.next_power_of_two(),
+            ).expect("Could not create ShapeRenderer index buffer."));
+        }
+
+        let vertex_buffer = self.vertex_buffer.as_ref().unwrap().slice(0..vertex_count)
+            .expect("Vertex buffer does not contain enough elements!");
+        vertex_buffer.write(&self.vertices.vertices);
+        let index_buffer = self.index_buffer.as_ref().unwrap().slice(0..index_count)
+            .expect("Index buffer does not contain enough elements!");
+        index_buffer.write(&self.vertices.indices);
 
         let uniforms = uniform! {
             projectionView: *self.projection_matrix.as_ref(),
@@ -180,8 +296,8 @@ impl ShapeRenderer {
             .. Default::default()
         };
 
-        target.draw(&vertex_buffer, &index_buffer, &self.shader, &uniforms, &params)
-            .expect("Failed to draw text.");
+        target.draw(vertex_buffer, index_buffer, &self.shader, &uniforms, &params)
+            .expect("Failed to draw shapes.");
 
         self.vertices.vertices.clear();
         self.vertices.indices.clear();
@@ -195,3 +311,28 @@ impl ShapeRenderer {
         self.projection_matrix
     }
 }
+
+fn circle_points(x: f32, y: f32, radius: f32, segments: u32) -> Vec<[f32; 2]> {
+    (0..segments)
+        .map(|i| {
+            let angle = i as f32 / segments as f32 * std::f32::consts::PI * 2.0;
+            [x + radius * angle.cos(), y + radius * angle.sin()]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn circle_points_lie_on_the_circle() {
+        let points = circle_points(10.0, -5.0, 3.0, DEFAULT_CIRCLE_SEGMENTS);
+        assert_eq!(points.len(), 32);
+        assert_eq!(points[0], [13.0, -5.0]);
+        for point in points {
+            let distance = ((point[0] - 10.0).powi(2) + (point[1] + 5.0).powi(2)).sqrt();
+            assert!((distance - 3.0).abs() < 1e-5);
+        }
+    }
+}
@@ -1,12 +1,16 @@
 use std::borrow::Borrow;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ops::Range;
 use std::rc::Rc;
 use std::thread;
 
-use glium::{DrawError, GlObject, Surface, uniform};
-use glium::uniforms::{Sampler, SamplerBehavior};
+use glium::{BlendingFunction, DrawError, GlObject, LinearBlendingFactor, Surface, uniform};
+use glium::uniforms::{AsUniformValue, Sampler, SamplerBehavior, UniformValue, Uniforms};
 pub use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
 use maybe_owned::MaybeOwned;
 
+use crate::graphics::Rect;
 use crate::graphics::texture::{TextureRegion, TextureRegionHolder};
 
 const VERTEX_SHADER_SRC: &str = include_str!("shaders/sprite.vs.glsl");
@@ -17,6 +21,8 @@ const QUAD_INDEX_SIZE: usize = 6;
 const BATCH_SIZE: usize = 1024;
 const BATCH_VERTEX_SIZE: usize = QUAD_VERTEX_SIZE * BATCH_SIZE;
 const BATCH_INDEX_SIZE: usize = QUAD_INDEX_SIZE * BATCH_SIZE;
+const DRAW_CALL_WARNING_MIN_SPRITES: u32 = 64;
+const DRAW_CALL_WARNING_RATIO: f32 = 0.5;
 
 
 #[derive(Clone, Copy, Debug)]
@@ -24,13 +30,54 @@ pub struct VertexData {
     pos: [f32; 2],
     tex_coords: [f32; 2],
     color: [f32; 4],
+    tint: [f32; 4],
+}
+glium::implement_vertex!(VertexData, pos, tex_coords, color, tint);
+
+impl VertexData {
+    /// A vertex without tint.
+    pub fn new(pos: [f32; 2], tex_coords: [f32; 2], color: [f32; 4]) -> Self {
+        VertexData { pos, tex_coords, color, tint: [0.0; 4] }
+    }
+
+    /// See `Sprite::set_tint`.
+    pub fn with_tint(mut self, tint: [f32; 4]) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub(crate) fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    fn quad(rect: Rect, tex_min: [f32; 2], tex_max: [f32; 2], color: [f32; 4]) -> [VertexData; 4] {
+        let (left, bottom) = (rect.x, rect.y);
+        let (right, top) = (rect.x + rect.width, rect.y + rect.height);
+
+        [
+            VertexData::new([left, top], [tex_min[0], tex_max[1]], color),
+            VertexData::new([right, top], [tex_max[0], tex_max[1]], color),
+            VertexData::new([right, bottom], [tex_max[0], tex_min[1]], color),
+            VertexData::new([left, bottom], [tex_min[0], tex_min[1]], color),
+        ]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillDirection {
+    LeftToRight,
+    RightToLeft,
+    BottomToTop,
+    TopToBottom,
 }
-glium::implement_vertex!(VertexData, pos, tex_coords, color);
 
 #[derive(Clone, Copy, Debug, Default)]
 pub struct SpriteDrawParams {
     pub sampler_behavior: SamplerBehavior,
     pub alpha_blending: bool,
+    pub blend_color: Option<[f32; 4]>,
+    pub ignore_vertex_color: bool,
+    pub sort_by_texture: bool,
 }
 
 impl SpriteDrawParams {
@@ -43,6 +90,30 @@ impl SpriteDrawParams {
         self
     }
 
+    /// Blends with a constant color instead of the sprite's own alpha, using
+    /// `result = source * color.a + destination * (1 - color.a)` for all channels. Drawing one
+    /// sprite over another with an increasing `color.a` cross-dissolves between the two.
+    pub fn blend_color(mut self, color: [f32; 4]) -> Self {
+        self.blend_color = Some(color);
+        self
+    }
+
+    /// Outputs the texel unmodified instead of multiplying it with the sprite color. This is a
+    /// uniform switch in the sprite shader, so it costs no extra draw calls or programs.
+    pub fn ignore_vertex_color(mut self, ignore: bool) -> Self {
+        self.ignore_vertex_color = ignore;
+        self
+    }
+
+    /// Groups queued sprites by texture before each flush, so interleaved textures don't break the
+    /// batch into a draw call per sprite. Sprites sharing a texture keep their order, but sprites
+    /// with different textures may end up drawn in a different order, so only use this when they
+    /// don't overlap or the order doesn't matter.
+    pub fn sort_by_texture(mut self, sort: bool) -> Self {
+        self.sort_by_texture = sort;
+        self
+    }
+
     pub fn wrap_function(mut self, function: SamplerWrapFunction) -> Self {
         self.sampler_behavior.wrap_function = (function, function, function);
         self
@@ -57,6 +128,24 @@ impl SpriteDrawParams {
         self.sampler_behavior.magnify_filter = filter;
         self
     }
+
+    pub(crate) fn blend(&self) -> glium::Blend {
+        if let Some(color) = self.blend_color {
+            let function = BlendingFunction::Addition {
+                source: LinearBlendingFactor::ConstantAlpha,
+                destination: LinearBlendingFactor::OneMinusConstantAlpha,
+            };
+            glium::Blend {
+                color: function,
+                alpha: function,
+                constant_value: (color[0], color[1], color[2], color[3]),
+            }
+        } else if self.alpha_blending {
+            glium::Blend::alpha_blending()
+        } else {
+            Default::default()
+        }
+    }
 }
 
 pub struct SpriteBatch<'a, 'b, S>
@@ -65,7 +154,11 @@ pub struct SpriteBatch<'a, 'b, S>
     renderer: &'a mut SpriteRenderer,
     target: &'b mut S,
     draw_params: SpriteDrawParams,
+    transform_stack: Vec<glm::Mat4>,
+    sprite_count: u32,
     draw_calls: u32,
+    drawn_bounds: Option<Rect>,
+    layer_mask: u32,
     finished: bool,
 }
 
@@ -79,109 +172,268 @@ impl<'a, 'b, S> SpriteBatch<'a, 'b, S>
             renderer,
             target,
             draw_params,
+            transform_stack: Vec::new(),
+            sprite_count: 0,
             draw_calls: 0,
+            drawn_bounds: None,
+            layer_mask: u32::MAX,
             finished: false,
         }
     }
 
     pub fn draw(&mut self, sprite: &Sprite) -> Result<(), DrawError> {
-        if self.renderer.sprite_queue.len() == BATCH_SIZE {
-            self.flush()?;
+        if !layer_visible(self.layer_mask, sprite.layer) {
+            return Ok(());
         }
+        self.push_vertices(sprite.get_vertex_data(), sprite.rc_texture())
+    }
+
+    /// Only sprites whose layer bit (`1 << layer`) is set in `mask` are drawn from now on, all
+    /// layers are visible by default. Hidden sprites are skipped before their vertices are
+    /// computed or queued, so they cost nothing. Quads drawn without a `Sprite`, like fill bars,
+    /// aren't affected.
+    pub fn set_layer_mask(&mut self, mask: u32) {
+        self.layer_mask = mask;
+    }
+
+    pub fn layer_mask(&self) -> u32 {
+        self.layer_mask
+    }
+
+    /// Combines `transform` with the current transform and makes the result current, so sprites
+    /// drawn afterwards are placed relative to it. Transforms are applied to the vertices on the
+    /// CPU as sprites are queued; with an empty stack the current transform is the identity.
+    pub fn push_transform(&mut self, transform: glm::Mat4) {
+        let combined = match self.transform_stack.last() {
+            Some(current) => current * transform,
+            None => transform,
+        };
+        self.transform_stack.push(combined);
+    }
+
+    pub fn pop_transform(&mut self) {
+        self.transform_stack.pop();
+    }
+
+    pub fn draw_grid(&mut self, region: &TextureRegion, origin: (f32, f32), cell: (f32, f32),
+                     cols: u32, rows: u32, color: [f32; 4]) -> Result<(), DrawError> {
+        assert!(cols > 0 && rows > 0, "`draw_grid` needs at least one column and one row!");
 
-        let vertices = sprite.get_vertex_data();
-        self.renderer.sprite_queue.push(vertices, sprite.rc_texture().clone());
+        let size = region.size();
+        let mut sprite = Sprite::from_texture_region(region);
+        sprite.set_origin(0.0, 0.0);
+        sprite.set_scale(cell.0 / size.x as f32, cell.1 / size.y as f32);
+        sprite.set_color(color);
+
+        for row in 0..rows {
+            for col in 0..cols {
+                sprite.set_position(origin.0 + col as f32 * cell.0, origin.1 + row as f32 * cell.1);
+                self.draw(&sprite)?;
+            }
+        }
 
         Ok(())
     }
 
+    /// Draws `background` stretched over `rect` and `fill` on top of it, clipped to `progress`
+    /// (`0.0..=1.0`) in `direction`. The fill's texture coordinates are clipped along with its
+    /// quad, so the fill texture is cut off rather than squashed. Directions assume a y-up
+    /// projection.
+    pub fn draw_fill_bar(&mut self, background: &TextureRegion, fill: &TextureRegion, rect: Rect,
+                         progress: f32, direction: FillDirection) -> Result<(), DrawError> {
+        let white = [1.0, 1.0, 1.0, 1.0];
+
+        let (tex_min, tex_max) = texture_bounds(background);
+        self.draw_quad(background.rc_texture(), rect, tex_min, tex_max, white)?;
+
+        let progress = progress.max(0.0).min(1.0);
+        if progress == 0.0 {
+            return Ok(());
+        }
+
+        let (mut tex_min, mut tex_max) = texture_bounds(fill);
+        let mut fill_rect = rect;
+        match direction {
+            FillDirection::LeftToRight => {
+                fill_rect.width *= progress;
+                tex_max[0] = tex_min[0] + (tex_max[0] - tex_min[0]) * progress;
+            }
+            FillDirection::RightToLeft => {
+                fill_rect.x += rect.width * (1.0 - progress);
+                fill_rect.width *= progress;
+                tex_min[0] = tex_max[0] - (tex_max[0] - tex_min[0]) * progress;
+            }
+            FillDirection::BottomToTop => {
+                fill_rect.height *= progress;
+                tex_max[1] = tex_min[1] + (tex_max[1] - tex_min[1]) * progress;
+            }
+            FillDirection::TopToBottom => {
+                fill_rect.y += rect.height * (1.0 - progress);
+                fill_rect.height *= progress;
+                tex_min[1] = tex_max[1] - (tex_max[1] - tex_min[1]) * progress;
+            }
+        }
+        self.draw_quad(fill.rc_texture(), fill_rect, tex_min, tex_max, white)
+    }
+
+    /// The axis-aligned union of everything drawn with this batch so far, after the batch
+    /// transforms but before the projection, or `None` if nothing was drawn. Each batch starts
+    /// empty. Rotated sprites contribute their whole bounding box, so this is meant for cropping
+    /// captures with `graphics::project`, not for exact hit tests.
+    pub fn drawn_bounds(&self) -> Option<Rect> {
+        self.drawn_bounds
+    }
+
     pub fn finish(mut self) -> Result<u32, DrawError> {
         self.flush()?;
         self.finished = true;
+        if cfg!(debug_assertions) {
+            self.warn_on_excessive_draw_calls();
+        }
         Ok(self.draw_calls)
     }
 
-    fn flush(&mut self) -> Result<(), DrawError> {
-        if self.renderer.sprite_queue.vertices.is_empty() {
-            return Ok(());
+    /// Draws the queued sprites to another `target` without clearing the queue, so the same
+    /// geometry can be emitted to several surfaces with only the target and projection differing.
+    ///
+    /// The batch holds a mutable borrow of its own target for as long as it lives, so `target` has
+    /// to be a different surface, e.g. a `SimpleFrameBuffer` over a reflection texture. Borrow that
+    /// surface only for the duration of this call. Sprites reach the extra targets only if this is
+    /// called before the queue fills up and flushes by itself, and before `finish` clears it.
+    pub fn flush_to<T: Surface>(&mut self, target: &mut T, projection: &glm::Mat4) -> Result<(), DrawError> {
+        self.sort_queue();
+        self.draw_calls += Self::draw_queue(self.renderer, &self.draw_params, projection, target)?;
+        Ok(())
+    }
+
+    pub(crate) fn draw_quad(&mut self, texture: &Rc<glium::Texture2d>, rect: Rect, tex_min: [f32; 2],
+                            tex_max: [f32; 2], color: [f32; 4]) -> Result<(), DrawError> {
+        self.push_vertices(VertexData::quad(rect, tex_min, tex_max, color), texture)
+    }
+
+    fn push_vertices(&mut self, mut vertices: [VertexData; 4], texture: &Rc<glium::Texture2d>) -> Result<(), DrawError> {
+        if self.renderer.sprite_queue.len() == BATCH_SIZE {
+            self.flush()?;
         }
 
-        let params = {
-            let blend = if self.draw_params.alpha_blending {
-                glium::Blend::alpha_blending()
-            } else {
-                Default::default()
-            };
-            glium::DrawParameters {
-                blend,
-                .. Default::default()
+        if let Some(transform) = self.transform_stack.last() {
+            for vertex in vertices.iter_mut() {
+                let pos = transform * glm::vec4(vertex.pos[0], vertex.pos[1], 0.0, 1.0);
+                vertex.pos = [pos.x, pos.y];
             }
-        };
+        }
+        self.drawn_bounds = Some(union_bounds(self.drawn_bounds, &vertices));
+        self.renderer.sprite_queue.push(vertices, texture.clone());
+        self.sprite_count += 1;
+
+        Ok(())
+    }
 
+    fn sort_queue(&mut self) {
+        if self.draw_params.sort_by_texture {
+            self.renderer.sprite_queue.sort_by_texture();
+        }
+    }
+
+    fn warn_on_excessive_draw_calls(&self) {
+        if self.sprite_count >= DRAW_CALL_WARNING_MIN_SPRITES
+            && self.draw_calls as f32 > self.sprite_count as f32 * DRAW_CALL_WARNING_RATIO
         {
-            let vertex_buffer = self.renderer.vertex_buffer.slice(0..self.renderer.sprite_queue.vertices.len())
-                .expect("Vertex buffer does not contain enough elements!");
-            vertex_buffer.write(&self.renderer.sprite_queue.vertices);
-        }
-
-        let mut render_texture = self.renderer.sprite_queue.textures[0].clone();
-        let mut offset = 0;
-        for (i, texture) in self.renderer.sprite_queue.textures.iter().enumerate().skip(1) {
-            if texture.get_id() != render_texture.get_id() {
-                {
-                    let sampler: Sampler<glium::Texture2d> = glium::uniforms::Sampler(
-                        render_texture.borrow(),
-                        self.draw_params.sampler_behavior,
-                    );
-                    let uniforms = uniform! {
-                        image: sampler,
-                        projectionView: *self.renderer.projection_matrix.as_ref(),
-                    };
-
-                    let (vertex_start, vertex_end) = (offset * QUAD_VERTEX_SIZE, i * QUAD_VERTEX_SIZE);
-                    let vertex_buffer = self.renderer.vertex_buffer.slice(vertex_start..vertex_end)
-                        .expect("Vertex buffer does not contain enough elements!");
-                    let (index_start, index_end) = (offset * QUAD_INDEX_SIZE, i * QUAD_INDEX_SIZE);
-                    let index_buffer = self.renderer.index_buffer.slice(index_start..index_end)
-                        .expect("Index buffer does not contain enough elements!");
-
-                    self.target.draw(vertex_buffer, index_buffer, &self.renderer.shader, &uniforms, &params)?;
-                }
-
-                self.draw_calls += 1;
-
-                offset = i;
-                render_texture = texture.clone();
-            }
+            eprintln!("SpriteBatch needed {} draw calls for {} sprites; consecutive sprites \
+                       switching textures are breaking up the batch.", self.draw_calls, self.sprite_count);
         }
+    }
+
+    /// Switches to new draw parameters for the sprites drawn from now on. Queued sprites are
+    /// flushed first with the old parameters, so every switch costs at least one draw call.
+    pub fn set_params(&mut self, draw_params: SpriteDrawParams) -> Result<(), DrawError> {
+        self.flush()?;
+        self.draw_params = draw_params;
+        Ok(())
+    }
+
+    pub fn draw_params(&self) -> SpriteDrawParams {
+        self.draw_params
+    }
+
+    /// Flushes and turns alpha blending off for opaque sprites. Drawing all opaque sprites first
+    /// and then switching with `begin_transparent` keeps the flush down to one per phase. A
+    /// `blend_color` set on the batch is left as is in both phases.
+    pub fn begin_opaque(&mut self) -> Result<(), DrawError> {
+        let draw_params = self.draw_params.alpha(false);
+        self.set_params(draw_params)
+    }
+
+    /// Flushes and turns alpha blending on for the sprites drawn after the opaque phase.
+    pub fn begin_transparent(&mut self) -> Result<(), DrawError> {
+        let draw_params = self.draw_params.alpha(true);
+        self.set_params(draw_params)
+    }
+
+    /// Number of sprites that can still be queued before the batch has to flush.
+    pub fn remaining_capacity(&self) -> usize {
+        BATCH_SIZE - self.renderer.sprite_queue.len()
+    }
+
+    /// Whether drawing `count` more sprites would flush the batch part way through, in which case
+    /// calling `flush` first moves the flush to a boundary of your choosing.
+    pub fn will_flush(&self, count: usize) -> bool {
+        count > self.remaining_capacity()
+    }
+
+    /// Draws and clears the queued sprites right away instead of waiting for the queue to fill up.
+    pub fn flush(&mut self) -> Result<(), DrawError> {
+        self.sort_queue();
+        let projection = self.renderer.projection_view();
+        self.draw_calls += Self::draw_queue(self.renderer, &self.draw_params, &projection, self.target)?;
+        self.renderer.sprite_queue.clear();
+
+        Ok(())
+    }
+
+    fn draw_queue<T: Surface>(renderer: &SpriteRenderer, draw_params: &SpriteDrawParams,
+                              projection: &glm::Mat4, target: &mut T) -> Result<u32, DrawError> {
+        if renderer.sprite_queue.vertices.is_empty() {
+            return Ok(0);
+        }
+
+        let params = glium::DrawParameters {
+            blend: draw_params.blend(),
+            .. Default::default()
+        };
 
         {
-            let i = self.renderer.sprite_queue.len();
+            let vertex_buffer = renderer.vertex_buffer.slice(0..renderer.sprite_queue.vertices.len())
+                .expect("Vertex buffer does not contain enough elements!");
+            vertex_buffer.write(&renderer.sprite_queue.vertices);
+        }
 
+        let mut draw_calls = 0;
+        for run in renderer.sprite_queue.texture_runs() {
             let sampler: Sampler<glium::Texture2d> = glium::uniforms::Sampler(
-                render_texture.borrow(),
-                self.draw_params.sampler_behavior,
+                renderer.sprite_queue.textures[run.start].borrow(),
+                draw_params.sampler_behavior,
             );
             let uniforms = uniform! {
                 image: sampler,
-                projectionView: *self.renderer.projection_matrix.as_ref(),
+                projectionView: *projection.as_ref(),
+                ignoreVertexColor: draw_params.ignore_vertex_color,
             };
+            let uniforms = WithOverrides { uniforms, overrides: &renderer.uniform_overrides };
 
-            let (vertex_start, vertex_end) = (offset * QUAD_VERTEX_SIZE, i * QUAD_VERTEX_SIZE);
-            let vertex_buffer = self.renderer.vertex_buffer.slice(vertex_start..vertex_end)
+            let (vertex_start, vertex_end) = (run.start * QUAD_VERTEX_SIZE, run.end * QUAD_VERTEX_SIZE);
+            let vertex_buffer = renderer.vertex_buffer.slice(vertex_start..vertex_end)
                 .expect("Vertex buffer does not contain enough elements!");
-            let (index_start, index_end) = (offset * QUAD_INDEX_SIZE, i * QUAD_INDEX_SIZE);
-            let index_buffer = self.renderer.index_buffer.slice(index_start..index_end)
+            let (index_start, index_end) = (run.start * QUAD_INDEX_SIZE, run.end * QUAD_INDEX_SIZE);
+            let index_buffer = renderer.index_buffer.slice(index_start..index_end)
                 .expect("Index buffer does not contain enough elements!");
 
-            self.target.draw(vertex_buffer, index_buffer, &self.renderer.shader, &uniforms, &params)?;
+            target.draw(vertex_buffer, index_buffer, &renderer.shader, &uniforms, &params)?;
 
-            self.draw_calls += 1;
+            draw_calls += 1;
         }
 
-        self.renderer.sprite_queue.clear();
-
-        Ok(())
+        Ok(draw_calls)
     }
 }
 
@@ -226,11 +478,59 @@ impl SpriteQueue {
     fn len(&self) -> usize {
         self.textures.len()
     }
+
+    fn texture_ids(&self) -> Vec<u32> {
+        self.textures.iter().map(|texture| texture.get_id()).collect()
+    }
+
+    /// Ranges of consecutive sprites sharing a texture, each drawn with one draw call.
+    fn texture_runs(&self) -> Vec<Range<usize>> {
+        runs(&self.texture_ids())
+    }
+
+    fn sort_by_texture(&mut self) {
+        let ids = self.texture_ids();
+        let order = stable_order(&ids);
+        if order.iter().enumerate().all(|(i, &index)| i == index) {
+            return;
+        }
+
+        let vertices: Vec<VertexData> = order.iter()
+            .flat_map(|&index| self.vertices[index * QUAD_VERTEX_SIZE..(index + 1) * QUAD_VERTEX_SIZE].iter().copied())
+            .collect();
+        let textures: Vec<_> = order.iter().map(|&index| self.textures[index].clone()).collect();
+        self.vertices.clear();
+        self.vertices.extend(vertices);
+        self.textures.clear();
+        self.textures.extend(textures);
+    }
+}
+
+/// Splits `keys` into ranges of consecutive equal keys.
+fn runs<K: PartialEq>(keys: &[K]) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for i in 1..=keys.len() {
+        if i == keys.len() || keys[i] != keys[start] {
+            runs.push(start..i);
+            start = i;
+        }
+    }
+    runs
+}
+
+/// Indices of `keys` in sorted order, keeping equal keys in their original order.
+fn stable_order<K: Ord>(keys: &[K]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_by_key(|&index| &keys[index]);
+    order
 }
 
 #[derive(Debug)]
 pub struct SpriteRenderer {
     projection_matrix: glm::Mat4,
+    view_offset: glm::Vec2,
+    uniform_overrides: HashMap<String, UniformOverride>,
     shader: glium::Program,
     vertex_buffer: glium::VertexBuffer<VertexData>,
     index_buffer: glium::IndexBuffer<u16>,
@@ -279,6 +579,8 @@ impl SpriteRenderer {
 
         Self {
             projection_matrix: projection,
+            view_offset: glm::vec2(0.0, 0.0),
+            uniform_overrides: HashMap::new(),
             shader,
             vertex_buffer,
             index_buffer,
@@ -291,29 +593,40 @@ impl SpriteRenderer {
     }
 
     pub fn draw<S: Surface>(&self, sprite: &Sprite, draw_params: SpriteDrawParams, target: &mut S) {
-        let vertices = sprite.get_vertex_data();
-
-        let vertex_buffer = self.vertex_buffer.slice(0..QUAD_VERTEX_SIZE)
-            .expect("Vertex buffer does not contain enough elements!");
-        vertex_buffer.write(&vertices);
-
         let sampler: Sampler<glium::Texture2d> = glium::uniforms::Sampler(
             sprite.texture(),
             draw_params.sampler_behavior,
         );
+        self.draw_vertices(&sprite.get_vertex_data(), sampler, &draw_params, target);
+    }
+
+    /// Draws the whole compressed `texture` stretched over `rect`, with the first stored row at the
+    /// top as in DDS files. Compressed textures cannot be batched, so every call is one draw call.
+    pub fn draw_compressed<S: Surface>(&self, texture: &glium::texture::CompressedTexture2d, rect: Rect,
+                                       color: [f32; 4], draw_params: SpriteDrawParams, target: &mut S) {
+        let vertices = VertexData::quad(rect, [0.0, 1.0], [1.0, 0.0], color);
+        let sampler: Sampler<glium::texture::CompressedTexture2d> = glium::uniforms::Sampler(
+            texture,
+            draw_params.sampler_behavior,
+        );
+        self.draw_vertices(&vertices, sampler, &draw_params, target);
+    }
+
+    fn draw_vertices<S: Surface, I: AsUniformValue>(&self, vertices: &[VertexData; 4], image: I,
+                                                    draw_params: &SpriteDrawParams, target: &mut S) {
+        let vertex_buffer = self.vertex_buffer.slice(0..QUAD_VERTEX_SIZE)
+            .expect("Vertex buffer does not contain enough elements!");
+        vertex_buffer.write(vertices);
 
         let uniforms = uniform! {
-            image: sampler,
-            projectionView: *self.projection_matrix.as_ref(),
+            image: image,
+            projectionView: *self.projection_view().as_ref(),
+            ignoreVertexColor: draw_params.ignore_vertex_color,
         };
+        let uniforms = WithOverrides { uniforms, overrides: &self.uniform_overrides };
 
-        let blend = if draw_params.alpha_blending {
-            glium::Blend::alpha_blending()
-        } else {
-            Default::default()
-        };
         let params = glium::DrawParameters {
-            blend,
+            blend: draw_params.blend(),
             .. Default::default()
         };
 
@@ -324,6 +637,11 @@ impl SpriteRenderer {
             .expect("Failed to draw sprites.");
     }
 
+    /// Sets the matrix mapping world coordinates to normalized device coordinates, e.g.
+    /// `glm::ortho(0.0, width, 0.0, height, -1.0, 1.0)` for pixels with y pointing up. It is passed
+    /// to the shader as is: nalgebra stores matrices column-major, which is what GLSL expects, so
+    /// a `glm` matrix must not be transposed. Matrices from row-major math libraries have to be
+    /// transposed before converting them.
     pub fn set_projection_matrix(&mut self, projection: glm::Mat4) {
         self.projection_matrix = projection;
     }
@@ -331,6 +649,131 @@ impl SpriteRenderer {
     pub fn get_projection_matrix(&self) -> glm::Mat4 {
         self.projection_matrix
     }
+
+    /// Translates everything drawn by `draw` and `SpriteBatch::flush` by `offset` world units on
+    /// top of the projection, e.g. for screen shake. Set it back to zero when the shake ends.
+    pub fn set_view_offset(&mut self, offset: glm::Vec2) {
+        self.view_offset = offset;
+    }
+
+    pub fn view_offset(&self) -> glm::Vec2 {
+        self.view_offset
+    }
+
+    /// Converts a window position like `Input::mouse_pos` into world coordinates of the current
+    /// projection including the view offset, see `graphics::unproject`.
+    pub fn unproject(&self, screen_pos: (i32, i32), screen_size: (u32, u32)) -> (f32, f32) {
+        crate::graphics::unproject(&self.projection_view(), screen_pos, screen_size)
+    }
+
+    /// Names of the active uniforms of the shader program, sorted, e.g. to list them in a tweaking
+    /// panel. Uniforms the GLSL compiler optimized away are not included.
+    pub fn uniform_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.shader.uniforms().map(|(name, _)| name.clone()).collect();
+        names.sort();
+        names
+    }
+
+    /// Sets uniform `name` for every following draw, replacing the value the renderer would pass
+    /// itself if there is one. Floats, `[f32; 2]`, `[f32; 3]`, `[f32; 4]`, `i32` and `bool` are
+    /// supported, see `UniformOverride`.
+    pub fn set_uniform_override<N: Into<String>, V: Into<UniformOverride>>(&mut self, name: N, value: V) {
+        self.uniform_overrides.insert(name.into(), value.into());
+    }
+
+    pub fn uniform_override(&self, name: &str) -> Option<UniformOverride> {
+        self.uniform_overrides.get(name).copied()
+    }
+
+    pub fn remove_uniform_override(&mut self, name: &str) -> Option<UniformOverride> {
+        self.uniform_overrides.remove(name)
+    }
+
+    pub fn clear_uniform_overrides(&mut self) {
+        self.uniform_overrides.clear();
+    }
+
+    fn projection_view(&self) -> glm::Mat4 {
+        glm::translate(&self.projection_matrix, &glm::vec3(self.view_offset.x, self.view_offset.y, 0.0))
+    }
+}
+
+/// A value for `SpriteRenderer::set_uniform_override`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UniformOverride {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Int(i32),
+    Bool(bool),
+}
+
+impl UniformOverride {
+    fn as_uniform_value(&self) -> UniformValue<'static> {
+        match *self {
+            UniformOverride::Float(value) => UniformValue::Float(value),
+            UniformOverride::Vec2(value) => UniformValue::Vec2(value),
+            UniformOverride::Vec3(value) => UniformValue::Vec3(value),
+            UniformOverride::Vec4(value) => UniformValue::Vec4(value),
+            UniformOverride::Int(value) => UniformValue::SignedInt(value),
+            UniformOverride::Bool(value) => UniformValue::Bool(value),
+        }
+    }
+}
+
+impl From<f32> for UniformOverride {
+    fn from(value: f32) -> Self {
+        UniformOverride::Float(value)
+    }
+}
+
+impl From<[f32; 2]> for UniformOverride {
+    fn from(value: [f32; 2]) -> Self {
+        UniformOverride::Vec2(value)
+    }
+}
+
+impl From<[f32; 3]> for UniformOverride {
+    fn from(value: [f32; 3]) -> Self {
+        UniformOverride::Vec3(value)
+    }
+}
+
+impl From<[f32; 4]> for UniformOverride {
+    fn from(value: [f32; 4]) -> Self {
+        UniformOverride::Vec4(value)
+    }
+}
+
+impl From<i32> for UniformOverride {
+    fn from(value: i32) -> Self {
+        UniformOverride::Int(value)
+    }
+}
+
+impl From<bool> for UniformOverride {
+    fn from(value: bool) -> Self {
+        UniformOverride::Bool(value)
+    }
+}
+
+struct WithOverrides<'o, U> {
+    uniforms: U,
+    overrides: &'o HashMap<String, UniformOverride>,
+}
+
+impl<'o, U: Uniforms> Uniforms for WithOverrides<'o, U> {
+    fn visit_values<'a, F: FnMut(&str, UniformValue<'a>)>(&'a self, mut output: F) {
+        self.uniforms.visit_values(|name, value| {
+            if !self.overrides.contains_key(name) {
+                output(name, value);
+            }
+        });
+        for (name, value) in self.overrides {
+            output(name, value.as_uniform_value());
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -342,8 +785,13 @@ pub struct Sprite<'a> {
     rotation: f32,
     scale: glm::TVec2<f32>,
     color: [f32; 4],
+    tint: [f32; 4],
     flip_x: bool,
     flip_y: bool,
+    uv_offset: glm::TVec2<f32>,
+    uv_repeat: glm::TVec2<f32>,
+    layer: u32,
+    vertex_cache: VertexCache,
 }
 
 impl<'a> Sprite<'a> {
@@ -368,13 +816,30 @@ impl<'a> Sprite<'a> {
             rotation: 0.0,
             scale: glm::vec2(1.0, 1.0),
             color: [1.0, 1.0, 1.0, 1.0],
+            tint: [0.0; 4],
             flip_x: false,
             flip_y: false,
+            uv_offset: glm::vec2(0.0, 0.0),
+            uv_repeat: glm::vec2(1.0, 1.0),
+            layer: 0,
+            vertex_cache: VertexCache::default(),
         }
     }
 
+    /// Keeps the sprite's vertices around between draws instead of recomputing them every time,
+    /// for sprites that rarely change such as backgrounds. Any setter that actually changes a
+    /// property invalidates the cache. Off by default.
+    pub fn set_cache_vertices(&mut self, enabled: bool) {
+        self.vertex_cache.enabled = enabled;
+        self.vertex_cache.invalidate();
+    }
+
+    pub fn caches_vertices(&self) -> bool {
+        self.vertex_cache.enabled
+    }
+
     pub fn set_position(&mut self, x: f32, y: f32) -> &mut Self {
-        self.position = glm::vec2(x, y);
+        set_changed(&mut self.position, glm::vec2(x, y), &mut self.vertex_cache);
         self
     }
 
@@ -383,23 +848,34 @@ impl<'a> Sprite<'a> {
     }
 
     pub fn set_origin(&mut self, x: f32, y: f32) {
-        self.origin = glm::vec2(x, y);
+        set_changed(&mut self.origin, glm::vec2(x, y), &mut self.vertex_cache);
     }
 
     pub fn origin(&self) -> (f32, f32) {
         (self.origin.x, self.origin.y)
     }
 
+    /// Sets the rotation in degrees. Rotations are stored in radians, so `set_rotation_radians`
+    /// avoids the conversion.
     pub fn set_rotation(&mut self, rotation: f32) {
-        self.rotation = rotation;
+        set_changed(&mut self.rotation, rotation.to_radians(), &mut self.vertex_cache);
     }
 
     pub fn rotation(&self) -> f32 {
+        self.rotation.to_degrees()
+    }
+
+    pub fn set_rotation_radians(&mut self, rotation: f32) {
+        set_changed(&mut self.rotation, rotation, &mut self.vertex_cache);
+    }
+
+    pub fn rotation_radians(&self) -> f32 {
         self.rotation
     }
 
+    /// A negative scale mirrors the sprite about its origin and is equivalent to the matching flip.
     pub fn set_scale(&mut self, scale_x: f32, scale_y: f32) {
-        self.scale = glm::vec2(scale_x, scale_y);
+        set_changed(&mut self.scale, glm::vec2(scale_x, scale_y), &mut self.vertex_cache);
     }
 
     pub fn set_uniform_scale(&mut self, scale: f32) {
@@ -411,7 +887,7 @@ impl<'a> Sprite<'a> {
     }
 
     pub fn set_flip_x(&mut self, flip_x: bool) {
-        self.flip_x = flip_x;
+        set_changed(&mut self.flip_x, flip_x, &mut self.vertex_cache);
     }
 
     pub fn flip_x(&self) -> bool {
@@ -419,7 +895,7 @@ impl<'a> Sprite<'a> {
     }
 
     pub fn set_flip_y(&mut self, flip_y: bool) {
-        self.flip_y = flip_y;
+        set_changed(&mut self.flip_y, flip_y, &mut self.vertex_cache);
     }
 
     pub fn flip_y(&self) -> bool {
@@ -427,63 +903,198 @@ impl<'a> Sprite<'a> {
     }
 
     pub fn set_color(&mut self, color: [f32; 4]) {
-        self.color = color;
+        set_changed(&mut self.color, color, &mut self.vertex_cache);
     }
 
     pub fn color(&self) -> [f32; 4] {
         self.color
     }
 
-    fn get_vertex_data(&self) -> [VertexData; 4] {
-        let model = {
-            let size = self.size();
-            let scaled_size = glm::vec2(size.x as f32 * self.scale.x, size.y as f32 * self.scale.y);
-            let pixel_origin = glm::vec2(scaled_size.x * self.origin.x, scaled_size.y * self.origin.y);
-            let position = self.position - pixel_origin;
-            let translate = glm::translation2d(&position);
-            let rotate = if self.rotation != 0.0 {
-                let rotation_matrix = glm::rotation2d(self.rotation.to_radians());
-                glm::translation2d(&pixel_origin) * rotation_matrix * glm::translation2d(&-pixel_origin)
-            } else {
-                glm::identity()
-            };
-            let scale = glm::scaling2d(&scaled_size);
-            translate * rotate * scale
-        };
+    /// Blends the sprite's RGB towards the tint's RGB by the tint's alpha, after the color is
+    /// applied, e.g. `[1.0, 1.0, 1.0, 0.8]` for a hit flash. The sprite's alpha is kept. Defaults
+    /// to `[0.0; 4]`, which has no effect. Also applies with `SpriteDrawParams::ignore_vertex_color`.
+    pub fn set_tint(&mut self, tint: [f32; 4]) {
+        set_changed(&mut self.tint, tint, &mut self.vertex_cache);
+    }
 
-        let tex_coords = self.texture_coordinates();
+    pub fn tint(&self) -> [f32; 4] {
+        self.tint
+    }
 
-        let tex_top_left = tex_coords[0];
-        let tex_top_right = tex_coords[1];
-        let tex_bottom_left = tex_coords[2];
-        let tex_bottom_right = tex_coords[3];
+    /// Puts the sprite on one of 32 layers (`0..32`) that batches can hide with
+    /// `SpriteBatch::set_layer_mask`. Defaults to layer `0`.
+    pub fn set_layer(&mut self, layer: u32) {
+        assert!(layer < 32, "Layer {} is out of range, sprites have 32 layers!", layer);
+        self.layer = layer;
+    }
 
-        let (tex_top_left, tex_top_right, tex_bottom_left, tex_bottom_right) = match (self.flip_x(), self.flip_y()) {
-            (false, false) => (tex_top_left, tex_top_right, tex_bottom_left, tex_bottom_right),
-            (true, false) => (tex_top_right, tex_top_left, tex_bottom_right, tex_bottom_left),
-            (false, true) => (tex_bottom_left, tex_bottom_right, tex_top_left, tex_top_right),
-            (true, true) => (tex_bottom_right, tex_bottom_left, tex_top_right, tex_top_left),
-        };
+    pub fn layer(&self) -> u32 {
+        self.layer
+    }
 
-        let pos_top_left = model * glm::vec3(0.0, 1.0, 1.0);
-        let pos_top_right = model * glm::vec3(1.0, 1.0, 1.0);
-        let pos_bottom_left = model * glm::vec3(1.0, 0.0, 1.0);
-        let pos_bottom_right = model * glm::vec3(0.0, 0.0, 1.0);
+    pub fn set_uv_offset(&mut self, u: f32, v: f32) {
+        set_changed(&mut self.uv_offset, glm::vec2(u, v), &mut self.vertex_cache);
+    }
 
-        let pos_top_left = [pos_top_left.x, pos_top_left.y];
-        let pos_top_right = [pos_top_right.x, pos_top_right.y];
-        let pos_bottom_left = [pos_bottom_left.x, pos_bottom_left.y];
-        let pos_bottom_right = [pos_bottom_right.x, pos_bottom_right.y];
+    pub fn uv_offset(&self) -> (f32, f32) {
+        (self.uv_offset.x, self.uv_offset.y)
+    }
 
-        let color = self.color();
+    /// Repeats the texture `x` by `y` times across the sprite. The texture coordinates are passed
+    /// through unclamped, so the sampler's wrap function decides what lies outside `0..1`, e.g.
+    /// `SpriteDrawParams::new().wrap_function(SamplerWrapFunction::Mirror)` mirrors every other
+    /// tile. Wrapping happens over the whole texture, so this is meant for regions that cover an
+    /// entire texture rather than a cell of an atlas.
+    pub fn set_uv_repeat(&mut self, x: f32, y: f32) {
+        set_changed(&mut self.uv_repeat, glm::vec2(x, y), &mut self.vertex_cache);
+    }
 
-        [
-            VertexData { pos: pos_top_left, tex_coords: tex_top_left, color },
-            VertexData { pos: pos_top_right, tex_coords: tex_top_right, color },
-            VertexData { pos: pos_bottom_left, tex_coords: tex_bottom_right, color },
-            VertexData { pos: pos_bottom_right, tex_coords: tex_bottom_left, color },
-        ]
+    pub fn uv_repeat(&self) -> (f32, f32) {
+        (self.uv_repeat.x, self.uv_repeat.y)
+    }
+
+    /// The model matrix the renderer uses for this sprite. It maps the unit square, with (0, 0) at
+    /// the bottom-left, to the sprite's quad in world units after origin, rotation and scale.
+    pub fn transform_matrix(&self) -> glm::Mat4 {
+        let size = self.size();
+        model_matrix(glm::vec2(size.x as f32, size.y as f32), self.position, self.origin, self.rotation, self.scale)
+    }
+
+    /// World space corners of the sprite's quad in the order top-left, top-right, bottom-right,
+    /// bottom-left of the unrotated, unmirrored sprite, i.e. the vertex order of `get_vertex_data`
+    /// for a positive scale.
+    pub fn corners(&self) -> [(f32, f32); 4] {
+        quad_corners(&self.transform_matrix())
+    }
+
+    pub(crate) fn get_vertex_data(&self) -> [VertexData; 4] {
+        self.vertex_cache.get_or_compute(|| {
+            let model = self.transform_matrix();
+            let tex_coords = repeat_texture_coordinates(self.texture_coordinates(), self.uv_offset, self.uv_repeat);
+
+            let mut vertices = quad_vertices(&model, tex_coords, self.flip_x, self.flip_y, self.scale, self.color);
+            for vertex in &mut vertices {
+                vertex.tint = self.tint;
+            }
+            vertices
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct VertexCache {
+    enabled: bool,
+    vertices: Cell<Option<[VertexData; 4]>>,
+}
+
+impl VertexCache {
+    fn get_or_compute<F: FnOnce() -> [VertexData; 4]>(&self, compute: F) -> [VertexData; 4] {
+        if !self.enabled {
+            return compute();
+        }
+        if let Some(vertices) = self.vertices.get() {
+            return vertices;
+        }
+        let vertices = compute();
+        self.vertices.set(Some(vertices));
+        vertices
+    }
+
+    fn invalidate(&mut self) {
+        *self.vertices.get_mut() = None;
+    }
+}
+
+fn set_changed<T: PartialEq>(field: &mut T, value: T, cache: &mut VertexCache) {
+    if *field != value {
+        *field = value;
+        cache.invalidate();
+    }
+}
+
+fn model_matrix(size: glm::Vec2, position: glm::Vec2, origin: glm::Vec2, rotation: f32,
+                scale: glm::Vec2) -> glm::Mat4 {
+    let scaled_size = glm::vec3(size.x * scale.x, size.y * scale.y, 1.0);
+    let pixel_origin = glm::vec3(scaled_size.x * origin.x, scaled_size.y * origin.y, 0.0);
+    let translate = glm::translation(&(glm::vec3(position.x, position.y, 0.0) - pixel_origin));
+    let rotate = if rotation != 0.0 {
+        let rotation_matrix = glm::rotation(rotation, &glm::vec3(0.0, 0.0, 1.0));
+        glm::translation(&pixel_origin) * rotation_matrix * glm::translation(&-pixel_origin)
+    } else {
+        glm::identity()
+    };
+    translate * rotate * glm::scaling(&scaled_size)
+}
+
+/// Builds the quad for a sprite. A negative scale mirrors the sprite about its origin, which looks
+/// the same as the matching flip, but it also mirrors the vertex positions and with them the
+/// winding. The vertex order is reversed in that case so the quad keeps facing the same way.
+fn quad_vertices(model: &glm::Mat4, tex_coords: [[f32; 2]; 4], flip_x: bool, flip_y: bool, scale: glm::Vec2,
+                 color: [f32; 4]) -> [VertexData; 4] {
+    let [tex_top_left, tex_top_right, tex_bottom_left, tex_bottom_right] = tex_coords;
+    let (tex_top_left, tex_top_right, tex_bottom_left, tex_bottom_right) = match (flip_x, flip_y) {
+        (false, false) => (tex_top_left, tex_top_right, tex_bottom_left, tex_bottom_right),
+        (true, false) => (tex_top_right, tex_top_left, tex_bottom_right, tex_bottom_left),
+        (false, true) => (tex_bottom_left, tex_bottom_right, tex_top_left, tex_top_right),
+        (true, true) => (tex_bottom_right, tex_bottom_left, tex_top_right, tex_top_left),
+    };
+
+    let [top_left, top_right, bottom_right, bottom_left] = quad_corners(model);
+
+    let top_left = VertexData::new([top_left.0, top_left.1], tex_top_left, color);
+    let top_right = VertexData::new([top_right.0, top_right.1], tex_top_right, color);
+    let bottom_right = VertexData::new([bottom_right.0, bottom_right.1], tex_bottom_right, color);
+    let bottom_left = VertexData::new([bottom_left.0, bottom_left.1], tex_bottom_left, color);
+
+    if scale.x * scale.y < 0.0 {
+        [top_left, bottom_left, bottom_right, top_right]
+    } else {
+        [top_left, top_right, bottom_right, bottom_left]
+    }
+}
+
+fn quad_corners(model: &glm::Mat4) -> [(f32, f32); 4] {
+    let corner = |x: f32, y: f32| {
+        let pos = model * glm::vec4(x, y, 0.0, 1.0);
+        (pos.x, pos.y)
+    };
+
+    [corner(0.0, 1.0), corner(1.0, 1.0), corner(1.0, 0.0), corner(0.0, 0.0)]
+}
+
+fn layer_visible(mask: u32, layer: u32) -> bool {
+    mask & (1 << layer) != 0
+}
+
+fn union_bounds(bounds: Option<Rect>, vertices: &[VertexData; 4]) -> Rect {
+    let (mut min, mut max) = match bounds {
+        Some(rect) => ([rect.x, rect.y], [rect.x + rect.width, rect.y + rect.height]),
+        None => (vertices[0].pos, vertices[0].pos),
+    };
+    for vertex in vertices {
+        for axis in 0..2 {
+            min[axis] = min[axis].min(vertex.pos[axis]);
+            max[axis] = max[axis].max(vertex.pos[axis]);
+        }
+    }
+    Rect::new(min[0], min[1], max[0] - min[0], max[1] - min[1])
+}
+
+fn texture_bounds(region: &TextureRegion) -> ([f32; 2], [f32; 2]) {
+    let offset = region.normalized_offset();
+    let size = region.normalized_size();
+    ([offset.x, offset.y], [offset.x + size.x, offset.y + size.y])
+}
+
+fn repeat_texture_coordinates(tex_coords: [[f32; 2]; 4], offset: glm::TVec2<f32>,
+                              repeat: glm::TVec2<f32>) -> [[f32; 2]; 4] {
+    let base = tex_coords[2];
+    let mut repeated = tex_coords;
+    for coords in repeated.iter_mut() {
+        coords[0] = base[0] + (coords[0] - base[0]) * repeat.x + offset.x;
+        coords[1] = base[1] + (coords[1] - base[1]) * repeat.y + offset.y;
     }
+    repeated
 }
 
 impl<'a> TextureRegionHolder for Sprite<'a> {
@@ -492,6 +1103,58 @@ impl<'a> TextureRegionHolder for Sprite<'a> {
     }
 }
 
+/// Reuses `Sprite` instances across frames so a varying number of sprites can be drawn without
+/// allocating. Sprites handed out by `get` stay valid until `clear`, which is usually called once at
+/// the start of every frame.
+pub struct SpritePool<'a> {
+    sprites: Vec<Sprite<'a>>,
+    in_use: usize,
+}
+
+impl<'a> SpritePool<'a> {
+    pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        SpritePool {
+            sprites: Vec::with_capacity(capacity),
+            in_use: 0,
+        }
+    }
+
+    /// Returns a sprite for `texture_region` with every other property reset to its default.
+    /// The region is borrowed, so swapping it on a reused sprite does not clone anything.
+    pub fn get(&mut self, texture_region: &'a TextureRegion) -> &mut Sprite<'a> {
+        let sprite = Sprite::from_texture_region(texture_region);
+        if self.in_use < self.sprites.len() {
+            self.sprites[self.in_use] = sprite;
+        } else {
+            self.sprites.push(sprite);
+        }
+        self.in_use += 1;
+
+        &mut self.sprites[self.in_use - 1]
+    }
+
+    /// Reclaims all sprites handed out since the last `clear` while keeping their memory around.
+    pub fn clear(&mut self) {
+        self.in_use = 0;
+    }
+
+    pub fn sprites(&self) -> &[Sprite<'a>] {
+        &self.sprites[..self.in_use]
+    }
+
+    pub fn len(&self) -> usize {
+        self.in_use
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.in_use == 0
+    }
+}
+
 pub trait DrawTexture {
     fn draw(&self, x: f32, y: f32) -> Sprite;
 }
@@ -503,3 +1166,258 @@ impl DrawTexture for TextureRegion {
         sprite
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::graphics::render_target::RenderTarget;
+    use crate::graphics::test_support::hidden_display;
+    use crate::graphics::texture::texture_from_pixels;
+
+    #[test]
+    fn repeated_texture_coordinates_are_not_clamped() {
+        let tex_coords = [[0.0, 1.0], [1.0, 1.0], [0.0, 0.0], [1.0, 0.0]];
+        let repeated = repeat_texture_coordinates(tex_coords, glm::vec2(0.5, 0.0), glm::vec2(2.0, 3.0));
+        assert_eq!(repeated, [[0.5, 3.0], [2.5, 3.0], [0.5, 0.0], [2.5, 0.0]]);
+    }
+
+    fn mirrored_quad(scale: glm::Vec2, flip_x: bool, flip_y: bool) -> [VertexData; 4] {
+        let tex_coords = [[0.0, 1.0], [1.0, 1.0], [0.0, 0.0], [1.0, 0.0]];
+        let model = model_matrix(glm::vec2(4.0, 2.0), glm::vec2(10.0, 20.0), glm::vec2(0.5, 0.5), 0.0, scale);
+        quad_vertices(&model, tex_coords, flip_x, flip_y, scale, [1.0; 4])
+    }
+
+    fn corners(vertices: &[VertexData; 4]) -> Vec<([f32; 2], [f32; 2])> {
+        let mut corners: Vec<_> = vertices.iter().map(|v| (v.pos, v.tex_coords)).collect();
+        corners.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        corners
+    }
+
+    fn winding(vertices: &[VertexData; 4]) -> f32 {
+        let [a, b, c] = [vertices[0].pos, vertices[1].pos, vertices[2].pos];
+        (b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])
+    }
+
+    #[test]
+    fn negative_scale_matches_flip() {
+        let plain = mirrored_quad(glm::vec2(1.0, 1.0), false, false);
+
+        let scaled_x = mirrored_quad(glm::vec2(-1.0, 1.0), false, false);
+        let flipped_x = mirrored_quad(glm::vec2(1.0, 1.0), true, false);
+        assert_eq!(corners(&scaled_x), corners(&flipped_x));
+        assert_eq!(winding(&scaled_x).signum(), winding(&plain).signum());
+
+        let scaled_y = mirrored_quad(glm::vec2(1.0, -1.0), false, false);
+        let flipped_y = mirrored_quad(glm::vec2(1.0, 1.0), false, true);
+        assert_eq!(corners(&scaled_y), corners(&flipped_y));
+        assert_eq!(winding(&scaled_y).signum(), winding(&plain).signum());
+
+        let scaled_xy = mirrored_quad(glm::vec2(-1.0, -1.0), false, false);
+        let flipped_xy = mirrored_quad(glm::vec2(1.0, 1.0), true, true);
+        assert_eq!(corners(&scaled_xy), corners(&flipped_xy));
+        assert_eq!(winding(&scaled_xy).signum(), winding(&plain).signum());
+    }
+
+    #[test]
+    fn quad_corners_follow_rotation() {
+        let model = model_matrix(glm::vec2(4.0, 2.0), glm::vec2(10.0, 20.0), glm::vec2(0.5, 0.5), 0.0,
+                                 glm::vec2(1.0, 1.0));
+        assert_eq!(quad_corners(&model), [(8.0, 21.0), (12.0, 21.0), (12.0, 19.0), (8.0, 19.0)]);
+
+        let model = model_matrix(glm::vec2(4.0, 2.0), glm::vec2(10.0, 20.0), glm::vec2(0.5, 0.5),
+                                 std::f32::consts::FRAC_PI_2, glm::vec2(1.0, 1.0));
+        let expected = [(9.0, 18.0), (9.0, 22.0), (11.0, 22.0), (11.0, 18.0)];
+        for (corner, expected) in quad_corners(&model).iter().zip(expected.iter()) {
+            assert!((corner.0 - expected.0).abs() < 1e-5 && (corner.1 - expected.1).abs() < 1e-5,
+                    "{:?} != {:?}", corner, expected);
+        }
+    }
+
+    #[test]
+    fn vertex_cache_is_only_invalidated_by_changes() {
+        let computed = Cell::new(0);
+        let compute = || {
+            computed.set(computed.get() + 1);
+            [VertexData::new([0.0, 0.0], [0.0, 0.0], [1.0; 4]); 4]
+        };
+        let mut cache = VertexCache { enabled: true, ..Default::default() };
+        let mut position = glm::vec2(1.0, 2.0);
+
+        cache.get_or_compute(compute);
+        cache.get_or_compute(compute);
+        assert_eq!(computed.get(), 1);
+
+        set_changed(&mut position, glm::vec2(1.0, 2.0), &mut cache);
+        cache.get_or_compute(compute);
+        assert_eq!(computed.get(), 1);
+
+        set_changed(&mut position, glm::vec2(3.0, 2.0), &mut cache);
+        cache.get_or_compute(compute);
+        assert_eq!(computed.get(), 2);
+        assert_eq!(position, glm::vec2(3.0, 2.0));
+    }
+
+    #[test]
+    fn uniform_overrides_replace_renderer_uniforms() {
+        let mut overrides = HashMap::new();
+        overrides.insert("strength".to_string(), UniformOverride::from(0.5));
+        overrides.insert("tint".to_string(), UniformOverride::from([1.0, 0.0, 0.0]));
+        let uniforms = WithOverrides {
+            uniforms: uniform! { strength: 2.0f32, ignoreVertexColor: true },
+            overrides: &overrides,
+        };
+
+        let mut visited = Vec::new();
+        uniforms.visit_values(|name, value| {
+            let value = match value {
+                UniformValue::Float(value) => format!("{}", value),
+                UniformValue::Vec3(value) => format!("{:?}", value),
+                UniformValue::Bool(value) => format!("{}", value),
+                _ => "other".to_string(),
+            };
+            visited.push((name.to_string(), value));
+        });
+        visited.sort();
+
+        assert_eq!(visited, vec![
+            ("ignoreVertexColor".to_string(), "true".to_string()),
+            ("strength".to_string(), "0.5".to_string()),
+            ("tint".to_string(), "[1.0, 0.0, 0.0]".to_string()),
+        ]);
+    }
+
+    /// Multiplies like `projectionView * vec4(pos, 0.0, 1.0)` in `sprite.vs.glsl`, reading the
+    /// matrix the way it is uploaded: as an array of columns.
+    fn shader_transform(projection: &glm::Mat4, pos: [f32; 2]) -> [f32; 2] {
+        let columns: &[[f32; 4]; 4] = projection.as_ref();
+        let v = [pos[0], pos[1], 0.0, 1.0];
+        let row = |i: usize| (0..4).map(|j| columns[j][i] * v[j]).sum::<f32>();
+        [row(0) / row(3), row(1) / row(3)]
+    }
+
+    #[test]
+    fn ortho_projection_reaches_the_shader_untransposed() {
+        let projection = glm::ortho(0.0, 800.0, 0.0, 600.0, -1.0, 1.0);
+        let model = model_matrix(glm::vec2(100.0, 50.0), glm::vec2(400.0, 300.0), glm::vec2(0.0, 0.0), 0.0,
+                                 glm::vec2(1.0, 1.0));
+        let vertices = quad_vertices(&model, [[0.0; 2]; 4], false, false, glm::vec2(1.0, 1.0), [1.0; 4]);
+
+        let expected = [[0.0, 1.0 / 6.0], [0.25, 1.0 / 6.0], [0.25, 0.0], [0.0, 0.0]];
+        for (vertex, expected) in vertices.iter().zip(expected.iter()) {
+            let ndc = shader_transform(&projection, vertex.pos);
+            assert!((ndc[0] - expected[0]).abs() < 1e-5 && (ndc[1] - expected[1]).abs() < 1e-5,
+                    "{:?} != {:?}", ndc, expected);
+        }
+    }
+
+    #[test]
+    fn runs_group_consecutive_keys() {
+        assert_eq!(runs::<u32>(&[]), vec![]);
+        assert_eq!(runs(&[7]), vec![0..1]);
+        assert_eq!(runs(&[1, 2, 1, 2]), vec![0..1, 1..2, 2..3, 3..4]);
+        assert_eq!(runs(&[1, 1, 2, 2, 2]), vec![0..2, 2..5]);
+    }
+
+    #[test]
+    fn stable_order_keeps_draw_order_within_a_texture() {
+        assert_eq!(stable_order(&[1, 2, 1, 2]), vec![0, 2, 1, 3]);
+    }
+
+    #[test]
+    #[ignore]
+    fn sorting_by_texture_merges_draw_calls() {
+        let (_sdl_context, display) = hidden_display();
+
+        let a = texture_from_pixels(&display, &[255; 4], 1, 1).unwrap();
+        let b = texture_from_pixels(&display, &[255; 4], 1, 1).unwrap();
+        let target = RenderTarget::new(&display, 4, 4);
+        let mut renderer = SpriteRenderer::new(&display, target.projection());
+        let sprites: Vec<Sprite> = [&a, &b, &a, &b].iter().map(|&texture| Sprite::new(texture.clone())).collect();
+        let mut draw_calls = |sorted: bool| {
+            let mut surface = target.surface();
+            let mut batch = renderer.begin_batch(SpriteDrawParams::new().sort_by_texture(sorted), &mut surface);
+            for sprite in &sprites {
+                batch.draw(sprite).unwrap();
+            }
+            batch.finish().unwrap()
+        };
+        assert_eq!(draw_calls(false), 4);
+        assert_eq!(draw_calls(true), 2);
+
+        let mut queue = SpriteQueue::new();
+        for (i, texture) in [&a, &b, &a, &b].iter().enumerate() {
+            let rect = Rect::new(i as f32, 0.0, 1.0, 1.0);
+            queue.push(VertexData::quad(rect, [0.0, 0.0], [1.0, 1.0], [1.0; 4]), (*texture).clone());
+        }
+        assert_eq!(queue.texture_runs().len(), 4);
+
+        queue.sort_by_texture();
+        assert_eq!(queue.texture_runs(), vec![0..2, 2..4]);
+        let first_x: Vec<f32> = queue.vertices.chunks(QUAD_VERTEX_SIZE)
+            .map(|quad| quad.iter().map(|v| v.pos[0]).fold(f32::MAX, f32::min))
+            .collect();
+        let (first, second) = if a.get_id() < b.get_id() { (0.0, 1.0) } else { (1.0, 0.0) };
+        assert_eq!(first_x, vec![first, first + 2.0, second, second + 2.0]);
+    }
+
+    // Timing comparison for 5k projectiles, run with `--release` and `--nocapture` to see it.
+    #[test]
+    #[ignore]
+    fn sprite_pool_benchmark() {
+        use std::time::Instant;
+
+        const PROJECTILES: usize = 5_000;
+        const FRAMES: usize = 200;
+
+        let (_sdl_context, display) = hidden_display();
+        let region = TextureRegion::from_pixels(&display, &[255; 4], 1, 1).unwrap();
+
+        let start = Instant::now();
+        let mut drawn = 0;
+        for frame in 0..FRAMES {
+            let mut sprites = Vec::new();
+            for i in 0..PROJECTILES {
+                let mut sprite = Sprite::from_texture_region(&region);
+                sprite.set_position(i as f32, frame as f32);
+                sprites.push(sprite);
+            }
+            drawn += sprites.len();
+        }
+        let allocating = start.elapsed();
+
+        let mut pool = SpritePool::new();
+        let start = Instant::now();
+        for frame in 0..FRAMES {
+            pool.clear();
+            for i in 0..PROJECTILES {
+                pool.get(&region).set_position(i as f32, frame as f32);
+            }
+            drawn += pool.len();
+        }
+        let pooled = start.elapsed();
+
+        assert_eq!(drawn, 2 * FRAMES * PROJECTILES);
+        eprintln!("{} projectiles over {} frames: Vec per frame {:?}, SpritePool {:?}",
+                  PROJECTILES, FRAMES, allocating, pooled);
+    }
+
+    #[test]
+    fn drawn_bounds_are_the_union_of_quads() {
+        let quad = |rect| VertexData::quad(rect, [0.0, 0.0], [1.0, 1.0], [1.0; 4]);
+        let first = union_bounds(None, &quad(Rect::new(10.0, 20.0, 5.0, 5.0)));
+        assert_eq!(first, Rect::new(10.0, 20.0, 5.0, 5.0));
+
+        let both = union_bounds(Some(first), &quad(Rect::new(-2.0, 22.0, 4.0, 10.0)));
+        assert_eq!(both, Rect::new(-2.0, 20.0, 17.0, 12.0));
+    }
+
+    #[test]
+    fn layer_mask_selects_layers_by_bit() {
+        let mask = (1 << 0) | (1 << 2);
+        assert!(layer_visible(mask, 0));
+        assert!(!layer_visible(mask, 1));
+        assert!(layer_visible(mask, 2));
+        assert!(layer_visible(u32::MAX, 31));
+        assert!(!layer_visible(0, 0));
+    }
+}
@@ -0,0 +1,31 @@
+//! Windows for tests that need an OpenGL context.
+//!
+//! Those tests are `#[ignore]`d since they need a video device with an OpenGL 3.3 driver. SDL can
+//! only be initialized on one thread at a time, so run them one by one with
+//! `cargo test -- --ignored --test-threads=1`.
+
+use glium_sdl2::{DisplayBuild, SDL2Facade};
+
+use crate::config::ApplicationGDXConfig;
+use crate::graphics::Graphics;
+
+/// A hidden 16x16 window with an OpenGL 3.3 core context. Keep the `Sdl` alive as long as the
+/// display.
+pub(crate) fn hidden_display() -> (sdl2::Sdl, SDL2Facade) {
+    let sdl_context = sdl2::init().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
+    video_subsystem.gl_attr().set_context_profile(sdl2::video::GLProfile::Core);
+    video_subsystem.gl_attr().set_context_version(3, 3);
+    let display = video_subsystem.window("Test", 16, 16)
+        .hidden()
+        .build_glium()
+        .unwrap();
+    (sdl_context, display)
+}
+
+/// `Graphics` for a hidden window built from `config`, see `hidden_display`.
+pub(crate) fn hidden_graphics(config: ApplicationGDXConfig) -> (sdl2::Sdl, Graphics) {
+    let sdl_context = sdl2::init().unwrap();
+    let graphics = Graphics::new(&config.with_start_hidden(true), &sdl_context);
+    (sdl_context, graphics)
+}
@@ -4,10 +4,18 @@ use std::ops::Deref;
 use glium::{Surface, Texture2d, uniform};
 use glium::vertex::EmptyVertexAttributes;
 pub use glyph_brush::{
-    rusttype::Scale,
+    rusttype::{Scale, SharedBytes},
     FontId, Section, VariedSection,
 };
-use glyph_brush::{BrushAction, BrushError, GlyphBrush, GlyphBrushBuilder};
+use glyph_brush::{BrushAction, BrushError, GlyphBrush, GlyphBrushBuilder, GlyphCruncher};
+
+use crate::graphics::shape::{DrawMode, ShapeRenderer};
+
+mod bitmap_font;
+mod ttf_font;
+
+pub use self::bitmap_font::{BitmapFont, BitmapFontData, BitmapFontError, Glyph};
+pub use self::ttf_font::{TtfFont, TtfFontError};
 
 const VERTEX_SHADER_SRC: &str = include_str!("shaders/text.vs.glsl");
 const FRAGMENT_SHADER_SRC: &str = include_str!("shaders/text.fs.glsl");
@@ -80,6 +88,51 @@ impl<'font> TextRenderer<'font> {
         self.glyph_brush.queue(section);
     }
 
+    /// Returns the width and height in pixels that `section` covers when drawn, without queueing
+    /// it. The same layout is used as for `queue`, so measured and drawn sizes match. Newlines and
+    /// glyph advances/kerning are accounted for; an empty section measures `(0.0, 0.0)`.
+    pub fn bounds<'a, S>(&mut self, section: S) -> (f32, f32)
+    where
+        S: Into<Cow<'a, VariedSection<'a>>>
+    {
+        self.glyph_brush.pixel_bounds(section)
+            .map(|rect| (rect.width() as f32, rect.height() as f32))
+            .unwrap_or((0.0, 0.0))
+    }
+
+    /// Adds a font for sections to select with the returned `FontId`. The built-in font is
+    /// `FontId(0)`.
+    pub fn add_font_bytes<B: Into<SharedBytes<'font>>>(&mut self, font_data: B) -> FontId {
+        self.glyph_brush.add_font_bytes(font_data)
+    }
+
+    /// Horizontal kerning adjustment in pixels between `a` followed by `b` at the given scale. It
+    /// is negative for pairs like "AV" that are drawn closer together. `queue` and `bounds` already
+    /// apply it through the glyph layout; this is for code that positions glyphs itself.
+    pub fn kerning(&self, font_id: FontId, scale: Scale, a: char, b: char) -> f32 {
+        self.glyph_brush.fonts().get(font_id.0)
+            .map(|font| font.pair_kerning(scale, a, b))
+            .unwrap_or(0.0)
+    }
+
+    /// Queues `section` together with a filled background rect covering all of its lines plus
+    /// `padding` on every side. The rect is queued on `shapes`, which has to use the same top-left
+    /// pixel projection as `draw_queued` and be drawn before the text so it ends up behind it.
+    pub fn queue_boxed<'a, S>(&mut self, shapes: &mut ShapeRenderer, section: S, padding: f32, bg_color: [f32; 4])
+    where
+        S: Into<Cow<'a, VariedSection<'a>>>
+    {
+        let section = section.into();
+        if let Some(rect) = self.glyph_brush.pixel_bounds(section.as_ref()) {
+            let width = rect.width() as f32 + padding * 2.0;
+            let height = rect.height() as f32 + padding * 2.0;
+            let center_x = (rect.min.x + rect.max.x) as f32 / 2.0;
+            let center_y = (rect.min.y + rect.max.y) as f32 / 2.0;
+            shapes.queue_rect(DrawMode::Fill, center_x, center_y, width, height, 0.0, bg_color);
+        }
+        self.glyph_brush.queue(section);
+    }
+
     pub fn draw_queued<F, S>(&mut self, display: &F, target: &mut S)
     where
         F: glium::backend::Facade + Deref<Target = glium::backend::Context>,
@@ -215,3 +268,40 @@ fn to_vertex(
         color,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use glyph_brush::rusttype::point;
+
+    use super::*;
+    use crate::graphics::test_support::hidden_display;
+
+    // VeraMono has no kerning, so the pair comes from a subset of DejaVu Sans with only "A", "V"
+    // and their kerning pair, see `assets/DejaVuSans-AV-LICENSE.txt`.
+    const KERNED_FONT_BYTES: &[u8] = include_bytes!("../../assets/DejaVuSans-AV.ttf");
+
+    #[test]
+    #[ignore]
+    fn kerning_reduces_the_advance_of_av() {
+        let (_sdl_context, display) = hidden_display();
+
+        let mut renderer = TextRenderer::new(&display);
+        let font_id = renderer.add_font_bytes(KERNED_FONT_BYTES);
+        let scale = Scale::uniform(100.0);
+        let kerning = renderer.kerning(font_id, scale, 'A', 'V');
+        assert!(kerning < -1.0, "expected a negative kerning for \"AV\", got {}", kerning);
+
+        // Width of "AV" with V placed right after the advance of A, i.e. without kerning.
+        let font = &renderer.glyph_brush.fonts()[font_id.0];
+        let a = font.glyph('A').scaled(scale);
+        let advance = a.h_metrics().advance_width;
+        let left = a.positioned(point(0.0, 0.0)).pixel_bounding_box().unwrap().min.x;
+        let right = font.glyph('V').scaled(scale).positioned(point(advance, 0.0))
+            .pixel_bounding_box().unwrap().max.x;
+        let unkerned_width = (right - left) as f32;
+
+        let (width, _) = renderer.bounds(Section { text: "AV", scale, font_id, .. Section::default() });
+        assert!((width - (unkerned_width + kerning)).abs() <= 1.0,
+                "\"AV\" is {} wide, expected {} reduced by {}", width, unkerned_width, kerning);
+    }
+}
@@ -0,0 +1,312 @@
+//! Fonts from AngelCode BMFont `.fnt` files in the text format, drawn as one sprite per glyph.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+use std::str::FromStr;
+
+use glium::{DrawError, Surface};
+
+use crate::graphics::Graphics;
+use crate::graphics::sprite::{Sprite, SpriteBatch};
+use crate::graphics::texture::{PageFileError, TextureRegion, load_pages, page_region};
+
+pub type BitmapFontError = PageFileError;
+
+/// A glyph as described in the `.fnt` file. `x` and `y` locate it on its page in pixels from the
+/// top-left, the offsets move it from the pen position with `y_offset` pointing down.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glyph {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub x_offset: f32,
+    pub y_offset: f32,
+    pub x_advance: f32,
+    pub page: usize,
+}
+
+/// The parsed contents of a `.fnt` file, without any textures.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BitmapFontData {
+    line_height: f32,
+    base: f32,
+    pages: Vec<String>,
+    glyphs: HashMap<char, Glyph>,
+    kernings: HashMap<(char, char), f32>,
+}
+
+impl BitmapFontData {
+    /// Parses the text format of BMFont. The XML and binary formats are not supported.
+    pub fn parse(source: &str) -> Result<Self, BitmapFontError> {
+        let mut data = BitmapFontData::default();
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let mut tokens = tokenize(line).into_iter();
+            let tag = match tokens.next() {
+                Some(tag) => tag,
+                None => continue,
+            };
+            let attributes: HashMap<&str, &str> = tokens
+                .filter_map(|token| {
+                    let mut parts = token.splitn(2, '=');
+                    Some((parts.next()?, parts.next()?.trim_matches('"')))
+                })
+                .collect();
+            let number = |key: &str| parse_attribute::<f32>(&attributes, key, line_number);
+            let integer = |key: &str| parse_attribute::<u32>(&attributes, key, line_number);
+
+            match tag {
+                "common" => {
+                    data.line_height = number("lineHeight")?;
+                    data.base = number("base")?;
+                }
+                "page" => {
+                    let id = integer("id")? as usize;
+                    let file = attributes.get("file").ok_or_else(|| missing_attribute("file", line_number))?;
+                    if data.pages.len() <= id {
+                        data.pages.resize(id + 1, String::new());
+                    }
+                    data.pages[id] = file.to_string();
+                }
+                "char" => {
+                    let character = code_point(integer("id")?, line_number)?;
+                    data.glyphs.insert(character, Glyph {
+                        x: integer("x")?,
+                        y: integer("y")?,
+                        width: integer("width")?,
+                        height: integer("height")?,
+                        x_offset: number("xoffset")?,
+                        y_offset: number("yoffset")?,
+                        x_advance: number("xadvance")?,
+                        page: integer("page")? as usize,
+                    });
+                }
+                "kerning" => {
+                    let first = code_point(integer("first")?, line_number)?;
+                    let second = code_point(integer("second")?, line_number)?;
+                    data.kernings.insert((first, second), number("amount")?);
+                }
+                _ => {}
+            }
+        }
+        Ok(data)
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// Distance from the top of a line to the baseline.
+    pub fn base(&self) -> f32 {
+        self.base
+    }
+
+    /// Texture file names by page id, relative to the `.fnt` file.
+    pub fn pages(&self) -> &[String] {
+        &self.pages
+    }
+
+    pub fn glyph(&self, character: char) -> Option<&Glyph> {
+        self.glyphs.get(&character)
+    }
+
+    /// Extra horizontal advance between `first` and `second`, usually negative.
+    pub fn kerning(&self, first: char, second: char) -> f32 {
+        self.kernings.get(&(first, second)).copied().unwrap_or(0.0)
+    }
+
+    /// Width of the widest line and height of all lines of `text`, see `BitmapFont::draw_text`.
+    pub fn measure(&self, text: &str) -> (f32, f32) {
+        self.layout(text, |_, _, _, _| {})
+    }
+
+    /// Calls `emit` with every glyph of `text` and its pen position relative to the top-left of
+    /// the text, y pointing up, and returns the size of the text. Characters without a glyph are
+    /// skipped.
+    fn layout<F: FnMut(char, &Glyph, f32, f32)>(&self, text: &str, mut emit: F) -> (f32, f32) {
+        if text.is_empty() {
+            return (0.0, 0.0);
+        }
+
+        let mut width: f32 = 0.0;
+        let mut line_count = 0;
+        for line in text.split('\n') {
+            let line_y = -(line_count as f32) * self.line_height;
+            let mut pen_x = 0.0;
+            let mut previous = None;
+            for character in line.chars() {
+                if let Some(glyph) = self.glyph(character) {
+                    if let Some(previous) = previous {
+                        pen_x += self.kerning(previous, character);
+                    }
+                    emit(character, glyph, pen_x, line_y);
+                    pen_x += glyph.x_advance;
+                    previous = Some(character);
+                }
+            }
+            width = width.max(pen_x);
+            line_count += 1;
+        }
+        (width, line_count as f32 * self.line_height)
+    }
+}
+
+pub struct BitmapFont {
+    data: BitmapFontData,
+    regions: HashMap<char, TextureRegion>,
+}
+
+impl BitmapFont {
+    /// Loads a `.fnt` file and its page textures, which are looked up next to it.
+    pub fn load<P: AsRef<Path>>(graphics: &Graphics, path: P) -> Result<Self, BitmapFontError> {
+        let path = path.as_ref();
+        let data = BitmapFontData::parse(&fs::read_to_string(path)?)?;
+        let pages = load_pages(graphics, path, data.pages())?;
+        Self::new(data, pages)
+    }
+
+    /// Creates the font from parsed data and its page textures by page id. The textures have to
+    /// be loaded upright, i.e. with `reversed` set for `Graphics::load_texture`.
+    pub fn new(data: BitmapFontData, pages: Vec<Rc<glium::Texture2d>>) -> Result<Self, BitmapFontError> {
+        let mut regions = HashMap::new();
+        for (&character, glyph) in &data.glyphs {
+            if glyph.width == 0 || glyph.height == 0 {
+                continue;
+            }
+            let region = page_region(&pages, glyph.page, glyph.x, glyph.y, (glyph.width, glyph.height))?;
+            regions.insert(character, region);
+        }
+
+        Ok(BitmapFont { data, regions })
+    }
+
+    pub fn data(&self) -> &BitmapFontData {
+        &self.data
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.data.line_height()
+    }
+
+    pub fn measure(&self, text: &str) -> (f32, f32) {
+        self.data.measure(text)
+    }
+
+    /// Draws `text` with its top-left corner at (`x`, `y`), one sprite per glyph. Lines are
+    /// separated by `'\n'` and advance downwards by the line height.
+    pub fn draw_text<S: Surface>(&self, batch: &mut SpriteBatch<S>, text: &str, x: f32,
+                                 y: f32) -> Result<(), DrawError> {
+        let mut result = Ok(());
+        self.data.layout(text, |character, glyph, pen_x, line_y| {
+            if result.is_err() {
+                return;
+            }
+            if let Some(region) = self.regions.get(&character) {
+                let mut sprite = Sprite::from_texture_region(region);
+                sprite.set_origin(0.0, 0.0);
+                sprite.set_position(x + pen_x + glyph.x_offset, y + line_y - glyph.y_offset - glyph.height as f32);
+                result = batch.draw(&sprite);
+            }
+        });
+        result
+    }
+}
+
+/// Splits a line at whitespace outside of double quotes.
+fn tokenize(line: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    for (index, character) in line.char_indices() {
+        if character == '"' {
+            quoted = !quoted;
+        }
+        if character.is_whitespace() && !quoted {
+            if let Some(start) = start.take() {
+                tokens.push(&line[start..index]);
+            }
+        } else if start.is_none() {
+            start = Some(index);
+        }
+    }
+    if let Some(start) = start {
+        tokens.push(&line[start..]);
+    }
+    tokens
+}
+
+fn parse_attribute<T: FromStr>(attributes: &HashMap<&str, &str>, key: &str,
+                               line: usize) -> Result<T, BitmapFontError> {
+    let value = attributes.get(key).ok_or_else(|| missing_attribute(key, line))?;
+    value.parse().map_err(|_| BitmapFontError::Parse {
+        line,
+        message: format!("invalid value {:?} for {}", value, key),
+    })
+}
+
+fn missing_attribute(key: &str, line: usize) -> BitmapFontError {
+    BitmapFontError::Parse { line, message: format!("missing {}", key) }
+}
+
+fn code_point(id: u32, line: usize) -> Result<char, BitmapFontError> {
+    std::char::from_u32(id).ok_or_else(|| BitmapFontError::Parse {
+        line,
+        message: format!("{} is not a valid character", id),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FONT: &str = r#"info face="Test Font" size=16 bold=0 italic=0
+common lineHeight=20 base=16 scaleW=64 scaleH=64 pages=1 packed=0
+page id=0 file="test font.png"
+chars count=3
+char id=32   x=0  y=0  width=0  height=0  xoffset=0  yoffset=0  xadvance=4  page=0 chnl=15
+char id=65   x=0  y=0  width=10 height=12 xoffset=0  yoffset=4  xadvance=10 page=0 chnl=15
+char id=86   x=10 y=0  width=10 height=12 xoffset=-1 yoffset=4  xadvance=9  page=0 chnl=15
+kernings count=1
+kerning first=65 second=86 amount=-2
+"#;
+
+    #[test]
+    fn fnt_file_is_parsed() {
+        let data = BitmapFontData::parse(FONT).unwrap();
+        assert_eq!(data.line_height(), 20.0);
+        assert_eq!(data.base(), 16.0);
+        assert_eq!(data.pages(), &["test font.png".to_string()]);
+        assert_eq!(data.glyph('V').unwrap().x_offset, -1.0);
+        assert_eq!(data.kerning('A', 'V'), -2.0);
+        assert_eq!(data.kerning('V', 'A'), 0.0);
+    }
+
+    #[test]
+    fn measure_applies_kerning_and_newlines() {
+        let data = BitmapFontData::parse(FONT).unwrap();
+        assert_eq!(data.measure(""), (0.0, 0.0));
+        assert_eq!(data.measure("AV"), (17.0, 20.0));
+        assert_eq!(data.measure("VA"), (19.0, 20.0));
+        assert_eq!(data.measure("A A\nV"), (24.0, 40.0));
+    }
+
+    #[test]
+    fn layout_places_lines_downwards() {
+        let data = BitmapFontData::parse(FONT).unwrap();
+        let mut positions = Vec::new();
+        data.layout("AV\nA", |character, _, x, y| positions.push((character, x, y)));
+        assert_eq!(positions, vec![('A', 0.0, 0.0), ('V', 8.0, 0.0), ('A', 0.0, -20.0)]);
+    }
+
+    #[test]
+    fn invalid_values_report_their_line() {
+        let source = "common lineHeight=20 base=16\nchar id=65 x=0 y=0 width=ten height=12";
+        match BitmapFontData::parse(source) {
+            Err(BitmapFontError::Parse { line: 2, .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! TrueType fonts rendered at a fixed pixel size, with glyphs rasterized on first use into a
+//! shared atlas texture that grows as needed.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use glium::{DrawError, Surface};
+use glium::backend::{Context, Facade};
+use glium::texture::RawImage2d;
+use glyph_brush::rusttype::{point, Font, Scale};
+
+use crate::graphics::sprite::{Sprite, SpriteBatch};
+use crate::graphics::texture::TextureRegion;
+
+const INITIAL_ATLAS_SIZE: (u32, u32) = (256, 256);
+const GLYPH_PADDING: u32 = 1;
+
+#[derive(Debug)]
+pub enum TtfFontError {
+    Io(io::Error),
+    InvalidFont(String),
+}
+
+impl fmt::Display for TtfFontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TtfFontError::Io(e) => write!(f, "could not read font file: {}", e),
+            TtfFontError::InvalidFont(reason) => write!(f, "invalid font: {}", reason),
+        }
+    }
+}
+
+impl Error for TtfFontError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TtfFontError::Io(e) => Some(e),
+            TtfFontError::InvalidFont(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for TtfFontError {
+    fn from(e: io::Error) -> Self {
+        TtfFontError::Io(e)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CachedGlyph {
+    offset: (u32, u32),
+    size: (u32, u32),
+    /// Offset of the glyph's top-left from the pen position on the baseline, y pointing down.
+    bearing: (f32, f32),
+}
+
+pub struct TtfFont {
+    context: Rc<Context>,
+    font: Font<'static>,
+    scale: Scale,
+    packer: AtlasPacker,
+    /// RGBA atlas pixels, bottom row first like the texture.
+    pixels: Vec<u8>,
+    atlas: Rc<glium::Texture2d>,
+    glyphs: HashMap<char, Option<CachedGlyph>>,
+}
+
+impl TtfFont {
+    pub fn load<F: Facade, P: AsRef<Path>>(display: &F, path: P, size_px: f32) -> Result<Self, TtfFontError> {
+        Self::from_bytes(display, fs::read(path)?, size_px)
+    }
+
+    pub fn from_bytes<F: Facade>(display: &F, bytes: Vec<u8>, size_px: f32) -> Result<Self, TtfFontError> {
+        let font = Font::from_bytes(bytes).map_err(|e| TtfFontError::InvalidFont(format!("{:?}", e)))?;
+        let packer = AtlasPacker::new(INITIAL_ATLAS_SIZE);
+        let pixels = vec![0; (packer.size.0 * packer.size.1 * 4) as usize];
+        let atlas = Rc::new(create_atlas(display, &pixels, packer.size));
+
+        Ok(TtfFont {
+            context: display.get_context().clone(),
+            font,
+            scale: Scale::uniform(size_px),
+            packer,
+            pixels,
+            atlas,
+            glyphs: HashMap::new(),
+        })
+    }
+
+    pub fn line_height(&self) -> f32 {
+        let v_metrics = self.font.v_metrics(self.scale);
+        v_metrics.ascent - v_metrics.descent + v_metrics.line_gap
+    }
+
+    /// The atlas with every glyph rasterized so far. It is replaced by a larger texture when it
+    /// runs out of space.
+    pub fn atlas(&self) -> &Rc<glium::Texture2d> {
+        &self.atlas
+    }
+
+    /// Width of the widest line and height of all lines of `text`, see `draw_text`. Only uses the
+    /// font metrics, so nothing is rasterized.
+    pub fn measure(&self, text: &str) -> (f32, f32) {
+        self.layout(text, |_, _, _| {})
+    }
+
+    /// Draws `text` with its top-left corner at (`x`, `y`), one sprite per glyph, rasterizing
+    /// glyphs that were not used before. Lines are separated by `'\n'` and advance downwards.
+    pub fn draw_text<S: Surface>(&mut self, batch: &mut SpriteBatch<S>, text: &str, x: f32,
+                                 y: f32) -> Result<(), DrawError> {
+        for character in text.chars() {
+            self.cache_glyph(character);
+        }
+
+        let ascent = self.font.v_metrics(self.scale).ascent;
+        let mut result = Ok(());
+        self.layout(text, |character, pen_x, line_y| {
+            if result.is_err() {
+                return;
+            }
+            if let Some(Some(glyph)) = self.glyphs.get(&character) {
+                let region = TextureRegion::with_sub_field(self.atlas.clone(), glyph.offset, glyph.size);
+                let mut sprite = Sprite::from_texture_region(region);
+                sprite.set_origin(0.0, 0.0);
+                let baseline = y + line_y - ascent;
+                sprite.set_position(x + pen_x + glyph.bearing.0, baseline - glyph.bearing.1 - glyph.size.1 as f32);
+                result = batch.draw(&sprite);
+            }
+        });
+        result
+    }
+
+    fn layout<F: FnMut(char, f32, f32)>(&self, text: &str, mut emit: F) -> (f32, f32) {
+        if text.is_empty() {
+            return (0.0, 0.0);
+        }
+
+        let line_height = self.line_height();
+        let mut width: f32 = 0.0;
+        let mut line_count = 0;
+        for line in text.split('\n') {
+            let line_y = -(line_count as f32) * line_height;
+            let mut pen_x = 0.0;
+            let mut previous = None;
+            for character in line.chars() {
+                if let Some(previous) = previous {
+                    pen_x += self.font.pair_kerning(self.scale, previous, character);
+                }
+                emit(character, pen_x, line_y);
+                pen_x += self.font.glyph(character).scaled(self.scale).h_metrics().advance_width;
+                previous = Some(character);
+            }
+            width = width.max(pen_x);
+            line_count += 1;
+        }
+        (width, line_count as f32 * line_height)
+    }
+
+    fn cache_glyph(&mut self, character: char) {
+        if self.glyphs.contains_key(&character) {
+            return;
+        }
+
+        let glyph = self.font.glyph(character).scaled(self.scale).positioned(point(0.0, 0.0));
+        let bounds = match glyph.pixel_bounding_box() {
+            Some(bounds) => bounds,
+            None => {
+                self.glyphs.insert(character, None);
+                return;
+            }
+        };
+        let size = (bounds.width() as u32, bounds.height() as u32);
+
+        let old_atlas_size = self.packer.size;
+        let offset = self.packer.insert(size);
+        if self.packer.size != old_atlas_size {
+            self.pixels = resize_pixels(&self.pixels, old_atlas_size, self.packer.size);
+        }
+
+        let atlas_width = self.packer.size.0;
+        let atlas_pixels = &mut self.pixels;
+        let mut glyph_pixels = vec![0; (size.0 * size.1 * 4) as usize];
+        glyph.draw(|x, y, coverage| {
+            let alpha = (coverage * 255.0).round() as u8;
+            let row = size.1 - 1 - y;
+            let glyph_index = ((row * size.0 + x) * 4) as usize;
+            glyph_pixels[glyph_index..glyph_index + 4].copy_from_slice(&[255, 255, 255, alpha]);
+            let atlas_index = (((offset.1 + row) * atlas_width + offset.0 + x) * 4) as usize;
+            atlas_pixels[atlas_index..atlas_index + 4].copy_from_slice(&[255, 255, 255, alpha]);
+        });
+
+        if self.packer.size != old_atlas_size {
+            self.atlas = Rc::new(create_atlas(&self.context, &self.pixels, self.packer.size));
+        } else {
+            let rect = glium::Rect { left: offset.0, bottom: offset.1, width: size.0, height: size.1 };
+            self.atlas.write(rect, RawImage2d::from_raw_rgba(glyph_pixels, size));
+        }
+
+        self.glyphs.insert(character, Some(CachedGlyph {
+            offset,
+            size,
+            bearing: (bounds.min.x as f32, bounds.min.y as f32),
+        }));
+    }
+}
+
+fn create_atlas<F: Facade + ?Sized>(display: &F, pixels: &[u8], size: (u32, u32)) -> glium::Texture2d {
+    glium::Texture2d::new(display, RawImage2d::from_raw_rgba(pixels.to_vec(), size))
+        .expect("Could not create glyph atlas texture.")
+}
+
+/// Copies `pixels` into a larger atlas, keeping every pixel at its position from the bottom-left.
+fn resize_pixels(pixels: &[u8], old_size: (u32, u32), new_size: (u32, u32)) -> Vec<u8> {
+    let mut resized = vec![0; (new_size.0 * new_size.1 * 4) as usize];
+    let old_row = (old_size.0 * 4) as usize;
+    let new_row = (new_size.0 * 4) as usize;
+    for row in 0..old_size.1 as usize {
+        resized[row * new_row..row * new_row + old_row].copy_from_slice(&pixels[row * old_row..(row + 1) * old_row]);
+    }
+    resized
+}
+
+/// Places glyphs in rows from the bottom-left. Instead of running out of space, the atlas doubles
+/// in height, or in width for glyphs wider than the atlas; placed glyphs keep their offsets.
+#[derive(Clone, Debug)]
+struct AtlasPacker {
+    size: (u32, u32),
+    pen_x: u32,
+    shelf_y: u32,
+    shelf_height: u32,
+}
+
+impl AtlasPacker {
+    fn new(size: (u32, u32)) -> Self {
+        AtlasPacker {
+            size,
+            pen_x: 0,
+            shelf_y: 0,
+            shelf_height: 0,
+        }
+    }
+
+    fn insert(&mut self, size: (u32, u32)) -> (u32, u32) {
+        let (width, height) = (size.0 + GLYPH_PADDING, size.1 + GLYPH_PADDING);
+        while width > self.size.0 {
+            self.size.0 *= 2;
+        }
+        if self.pen_x + width > self.size.0 {
+            self.shelf_y += self.shelf_height;
+            self.pen_x = 0;
+            self.shelf_height = 0;
+        }
+        while self.shelf_y + height > self.size.1 {
+            self.size.1 *= 2;
+        }
+
+        let offset = (self.pen_x, self.shelf_y);
+        self.pen_x += width;
+        self.shelf_height = self.shelf_height.max(height);
+        offset
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn packer_grows_instead_of_overflowing() {
+        let mut packer = AtlasPacker::new((16, 16));
+        let offsets: Vec<_> = (0..10).map(|_| packer.insert((7, 7))).collect();
+
+        assert_eq!(packer.size, (16, 64));
+        assert_eq!(&offsets[..3], &[(0, 0), (8, 0), (0, 8)]);
+        for (i, a) in offsets.iter().enumerate() {
+            assert!(a.0 + 7 <= packer.size.0 && a.1 + 7 <= packer.size.1);
+            for b in &offsets[i + 1..] {
+                assert!(a.0 + 8 <= b.0 || b.0 + 8 <= a.0 || a.1 + 8 <= b.1 || b.1 + 8 <= a.1);
+            }
+        }
+
+        // Widening the atlas leaves room next to the current shelf, so the wide glyph goes there.
+        assert_eq!(packer.insert((40, 3)), (16, 32));
+        assert_eq!(packer.size, (64, 64));
+        assert_eq!(packer.insert((7, 7)), (0, 40));
+    }
+
+    #[test]
+    fn resized_pixels_keep_their_position() {
+        let pixels: Vec<u8> = (0..2 * 2 * 4).collect();
+        let resized = resize_pixels(&pixels, (2, 2), (4, 4));
+        assert_eq!(resized.len(), 4 * 4 * 4);
+        assert_eq!(&resized[..8], &pixels[..8]);
+        assert_eq!(&resized[16..24], &pixels[8..16]);
+        assert!(resized[8..16].iter().all(|&p| p == 0));
+    }
+}
@@ -1,6 +1,121 @@
 use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
 use std::rc::Rc;
 
+mod atlas;
+mod page_file;
+
+pub use self::atlas::{AtlasError, AtlasRegionData, TextureAtlas, TextureAtlasData};
+pub use self::page_file::PageFileError;
+pub(crate) use self::page_file::{load_pages, page_region};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegionError {
+    OutOfBounds {
+        offset: (u32, u32),
+        size: (u32, u32),
+        texture_size: (u32, u32),
+    },
+    CellOutOfGrid {
+        name: String,
+        index: usize,
+        cell_count: usize,
+    },
+    MissingPage {
+        page: usize,
+    },
+}
+
+impl fmt::Display for RegionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RegionError::OutOfBounds { offset, size, texture_size } =>
+                write!(f, "region at {:?} with size {:?} exceeds the texture size {:?}", offset, size, texture_size),
+            RegionError::CellOutOfGrid { name, index, cell_count } =>
+                write!(f, "cell {} for region {:?} is outside the grid of {} cells", index, name, cell_count),
+            RegionError::MissingPage { page } => write!(f, "page {} does not exist", page),
+        }
+    }
+}
+
+impl Error for RegionError {}
+
+#[derive(Debug)]
+pub enum TextureLoadError {
+    PixelCountMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    TooLarge {
+        size: (u32, u32),
+        max_size: u32,
+    },
+    Image(image::ImageError),
+    Creation(glium::texture::TextureCreationError),
+}
+
+impl fmt::Display for TextureLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureLoadError::PixelCountMismatch { expected, actual } =>
+                write!(f, "expected {} bytes of RGBA pixel data but got {}", expected, actual),
+            TextureLoadError::TooLarge { size, max_size } =>
+                write!(f, "texture size {}x{} exceeds the maximum of {} supported by the GPU", size.0, size.1, max_size),
+            TextureLoadError::Image(e) => write!(f, "could not decode image: {}", e),
+            TextureLoadError::Creation(e) => write!(f, "could not create texture: {:?}", e),
+        }
+    }
+}
+
+impl Error for TextureLoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextureLoadError::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for TextureLoadError {
+    fn from(e: image::ImageError) -> Self {
+        TextureLoadError::Image(e)
+    }
+}
+
+impl From<glium::texture::TextureCreationError> for TextureLoadError {
+    fn from(e: glium::texture::TextureCreationError) -> Self {
+        TextureLoadError::Creation(e)
+    }
+}
+
+/// Creates a texture from tightly packed 8-bit RGBA pixels, given row by row from the top like an
+/// image file, so it shows up upright on sprites.
+pub fn texture_from_pixels<F: glium::backend::Facade>(display: &F, pixels: &[u8], width: u32,
+                                                      height: u32) -> Result<Rc<glium::Texture2d>, TextureLoadError> {
+    check_pixel_count(pixels.len(), width, height)?;
+    let image = glium::texture::RawImage2d::from_raw_rgba_reversed(pixels, (width, height));
+    Ok(Rc::new(glium::Texture2d::new(display, image)?))
+}
+
+pub(crate) fn check_texture_size(size: (u32, u32), max_size: u32) -> Result<(), TextureLoadError> {
+    if size.0 <= max_size && size.1 <= max_size {
+        Ok(())
+    } else {
+        Err(TextureLoadError::TooLarge { size, max_size })
+    }
+}
+
+fn check_pixel_count(len: usize, width: u32, height: u32) -> Result<(), TextureLoadError> {
+    let expected = width as usize * height as usize * 4;
+    if len == expected {
+        Ok(())
+    } else {
+        Err(TextureLoadError::PixelCountMismatch { expected, actual: len })
+    }
+}
+
 #[derive(Clone)]
 pub struct TextureRegion {
     texture: Rc<glium::Texture2d>,
@@ -10,6 +125,7 @@ pub struct TextureRegion {
 
     normalized_offset: glm::TVec2<f32>,
     normalized_size: glm::TVec2<f32>,
+    rotated: bool,
 }
 
 impl TextureRegion {
@@ -25,11 +141,14 @@ impl TextureRegion {
 
             normalized_offset: glm::vec2(0.0, 0.0),
             normalized_size: glm::vec2(1.0, 1.0),
+            rotated: false,
         }
     }
 
     pub fn with_sub_field(texture: Rc<glium::Texture2d>, offset: (u32, u32), size: (u32, u32)) -> Self {
         let texture_size = texture.dimensions();
+        debug_assert!(check_sub_field(texture_size, offset, size).is_ok(),
+                      "Region at {:?} with size {:?} exceeds the texture size {:?}!", offset, size, texture_size);
         let texture_size = glm::vec2(texture_size.0, texture_size.1);
 
         let offset = glm::vec2(offset.0, offset.1);
@@ -48,10 +167,29 @@ impl TextureRegion {
 
             normalized_offset,
             normalized_size,
-
+            rotated: false,
         }
     }
 
+    pub fn try_with_sub_field(texture: Rc<glium::Texture2d>, offset: (u32, u32),
+                              size: (u32, u32)) -> Result<Self, RegionError> {
+        check_sub_field(texture.dimensions(), offset, size)?;
+        Ok(Self::with_sub_field(texture, offset, size))
+    }
+
+    /// Region with its top-left corner at (`x`, `y`) counted from the top-left of `texture`, as
+    /// in atlas and font files, on a texture loaded upright.
+    pub fn from_top_left(texture: Rc<glium::Texture2d>, x: u32, y: u32, width: u32,
+                         height: u32) -> Result<Self, RegionError> {
+        let offset = flip_top_left(texture.dimensions(), (x, y), (width, height))?;
+        Self::try_with_sub_field(texture, offset, (width, height))
+    }
+
+    pub fn from_pixels<F: glium::backend::Facade>(display: &F, pixels: &[u8], width: u32,
+                                                  height: u32) -> Result<Self, TextureLoadError> {
+        Ok(Self::new(texture_from_pixels(display, pixels, width, height)?))
+    }
+
     pub fn split(texture: Rc<glium::Texture2d>, size: (u32, u32)) -> Vec<Self> {
         let texture_size = texture.dimensions();
 
@@ -67,6 +205,37 @@ impl TextureRegion {
         regions
     }
 
+    /// Splits `texture` into cells like `split` and names some of them. Each name maps to a cell
+    /// index counted row by row like the positions in the `Vec` returned by `split`.
+    pub fn split_named(texture: Rc<glium::Texture2d>, size: (u32, u32),
+                       names: &[(&str, usize)]) -> Result<HashMap<String, Self>, RegionError> {
+        let regions = Self::split(texture, size);
+
+        names.iter()
+            .map(|&(name, index)| {
+                regions.get(index)
+                    .map(|region| (name.to_string(), region.clone()))
+                    .ok_or_else(|| RegionError::CellOutOfGrid {
+                        name: name.to_string(),
+                        index,
+                        cell_count: regions.len(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Marks the texels of the region as rotated by 90 degrees counterclockwise, like regions
+    /// TexturePacker rotated to pack them. `size` and `texture_coordinates` then describe the
+    /// region upright, so a sprite of it is drawn the way the image was before packing.
+    pub fn with_rotated(mut self, rotated: bool) -> Self {
+        self.rotated = rotated;
+        self
+    }
+
+    pub fn is_rotated(&self) -> bool {
+        self.rotated
+    }
+
     pub fn texture(&self) -> &glium::Texture2d {
         self.texture.borrow()
     }
@@ -83,8 +252,14 @@ impl TextureRegion {
         self.offset
     }
 
+    /// Size of the region as drawn, i.e. the texel size with width and height swapped for a
+    /// rotated region.
     pub fn size(&self) -> glm::TVec2<u32> {
-        self.size
+        if self.rotated {
+            glm::vec2(self.size.y, self.size.x)
+        } else {
+            self.size
+        }
     }
 
     pub fn normalized_offset(&self) -> glm::TVec2<f32> {
@@ -101,7 +276,38 @@ impl TextureRegion {
         let bot_left = [self.normalized_offset.x, self.normalized_offset.y];
         let bot_right = [self.normalized_offset.x + self.normalized_size.x, self.normalized_offset.y];
 
-        [top_left, top_right, bot_left, bot_right]
+        if self.rotated {
+            rotate_clockwise([top_left, top_right, bot_left, bot_right])
+        } else {
+            [top_left, top_right, bot_left, bot_right]
+        }
+    }
+}
+
+/// Texture coordinates in the order top-left, top-right, bottom-left, bottom-right that show texels
+/// rotated counterclockwise upright again.
+fn rotate_clockwise(tex_coords: [[f32; 2]; 4]) -> [[f32; 2]; 4] {
+    let [top_left, top_right, bot_left, bot_right] = tex_coords;
+    [bot_left, top_left, bot_right, top_right]
+}
+
+/// Offset from the bottom-left of a region given from the top-left of the texture.
+fn flip_top_left(texture_size: (u32, u32), offset: (u32, u32), size: (u32, u32)) -> Result<(u32, u32), RegionError> {
+    offset.1.checked_add(size.1)
+        .and_then(|bottom| texture_size.1.checked_sub(bottom))
+        .map(|y| (offset.0, y))
+        .ok_or(RegionError::OutOfBounds { offset, size, texture_size })
+}
+
+fn check_sub_field(texture_size: (u32, u32), offset: (u32, u32), size: (u32, u32)) -> Result<(), RegionError> {
+    let fits = |offset: u32, size: u32, texture_size: u32| {
+        offset.checked_add(size).map_or(false, |end| end <= texture_size)
+    };
+
+    if fits(offset.0, size.0, texture_size.0) && fits(offset.1, size.1, texture_size.1) {
+        Ok(())
+    } else {
+        Err(RegionError::OutOfBounds { offset, size, texture_size })
     }
 }
 
@@ -146,3 +352,51 @@ impl TextureRegionHolder for TextureRegion {
         self
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sub_field_touching_the_edges_is_valid() {
+        assert_eq!(check_sub_field((64, 32), (0, 0), (64, 32)), Ok(()));
+        assert_eq!(check_sub_field((64, 32), (48, 16), (16, 16)), Ok(()));
+    }
+
+    #[test]
+    fn sub_field_past_the_edges_is_rejected() {
+        assert!(check_sub_field((64, 32), (49, 0), (16, 16)).is_err());
+        assert!(check_sub_field((64, 32), (0, 17), (16, 16)).is_err());
+        assert!(check_sub_field((64, 32), (u32::MAX, 0), (1, 1)).is_err());
+    }
+
+    #[test]
+    fn rotated_texels_are_turned_upright() {
+        let (tl, tr, bl, br) = ([0.0, 1.0], [1.0, 1.0], [0.0, 0.0], [1.0, 0.0]);
+        assert_eq!(rotate_clockwise([tl, tr, bl, br]), [bl, tl, br, tr]);
+    }
+
+    #[test]
+    fn top_left_offsets_are_flipped_to_the_bottom_left() {
+        assert_eq!(flip_top_left((64, 32), (8, 0), (16, 8)), Ok((8, 24)));
+        assert_eq!(flip_top_left((64, 32), (0, 24), (16, 8)), Ok((0, 0)));
+        assert!(flip_top_left((64, 32), (0, 25), (16, 8)).is_err());
+        assert!(flip_top_left((64, 32), (0, u32::MAX), (16, 8)).is_err());
+    }
+
+    #[test]
+    fn texture_size_is_limited_per_side() {
+        assert!(check_texture_size((2048, 2048), 2048).is_ok());
+        assert!(check_texture_size((4096, 16), 2048).is_err());
+        assert!(check_texture_size((16, 2049), 2048).is_err());
+    }
+
+    #[test]
+    fn pixel_count_has_to_match_rgba_dimensions() {
+        assert!(check_pixel_count(2 * 3 * 4, 2, 3).is_ok());
+        match check_pixel_count(2 * 3 * 3, 2, 3) {
+            Err(TextureLoadError::PixelCountMismatch { expected: 24, actual: 18 }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
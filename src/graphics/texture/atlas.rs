@@ -0,0 +1,359 @@
+//! Texture atlases in the `.atlas` format written by libGDX's TexturePacker, both the legacy
+//! format (`xy`, `size`, `orig`, `offset`, `rotate: true`) and the newer one (`bounds`, `offsets`,
+//! `rotate: 90`).
+
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::graphics::Graphics;
+use crate::graphics::texture::{PageFileError, TextureRegion, load_pages, page_region};
+
+pub type AtlasError = PageFileError;
+
+/// A region as described in the `.atlas` file. `x` and `y` locate it on its page in pixels from
+/// the top-left; `width` and `height` are its size before packing, so a rotated region covers
+/// `height` x `width` pixels of the page.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasRegionData {
+    pub page: usize,
+    pub name: String,
+    /// Frame number of regions sharing a name, `-1` for regions that are not part of a sequence.
+    pub index: i32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Whether TexturePacker rotated the region by 90 degrees counterclockwise to pack it.
+    pub rotated: bool,
+    /// Size of the image before whitespace was stripped.
+    pub original_size: (u32, u32),
+    /// Position of the packed pixels within the original image, from its bottom-left.
+    pub offset: (f32, f32),
+}
+
+impl AtlasRegionData {
+    /// Size of the region's pixels on the page.
+    pub fn packed_size(&self) -> (u32, u32) {
+        if self.rotated {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+}
+
+/// The parsed contents of an `.atlas` file, without any textures.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextureAtlasData {
+    pages: Vec<String>,
+    regions: Vec<AtlasRegionData>,
+}
+
+impl TextureAtlasData {
+    pub fn parse(source: &str) -> Result<Self, AtlasError> {
+        let mut data = TextureAtlasData::default();
+        let mut expect_page = true;
+        let mut in_region = false;
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                expect_page = true;
+                continue;
+            }
+
+            let field = trimmed.find(':').map(|colon| (trimmed[..colon].trim(), trimmed[colon + 1..].trim()));
+            match field {
+                None if expect_page => {
+                    data.pages.push(trimmed.to_string());
+                    expect_page = false;
+                    in_region = false;
+                }
+                None => {
+                    let page = data.pages.len() - 1;
+                    data.regions.push(AtlasRegionData {
+                        page,
+                        name: trimmed.to_string(),
+                        index: -1,
+                        x: 0,
+                        y: 0,
+                        width: 0,
+                        height: 0,
+                        rotated: false,
+                        original_size: (0, 0),
+                        offset: (0.0, 0.0),
+                    });
+                    in_region = true;
+                }
+                Some(_) if expect_page => {
+                    return Err(parse_error(line_number, "field before the first page".to_string()));
+                }
+                Some((key, value)) if in_region => {
+                    let region = data.regions.last_mut().unwrap();
+                    parse_region_field(region, key, value, line_number)?;
+                }
+                // Page fields like size, format, filter and repeat are taken from the texture.
+                Some(_) => {}
+            }
+        }
+
+        for region in &mut data.regions {
+            if region.original_size == (0, 0) {
+                region.original_size = (region.width, region.height);
+            }
+        }
+        Ok(data)
+    }
+
+    /// Texture file names by page, relative to the `.atlas` file.
+    pub fn pages(&self) -> &[String] {
+        &self.pages
+    }
+
+    pub fn regions(&self) -> &[AtlasRegionData] {
+        &self.regions
+    }
+
+    /// Indices into `regions` of all regions called `name`, ordered by their index.
+    fn matching(&self, name: &str) -> Vec<usize> {
+        let mut matching: Vec<usize> = (0..self.regions.len())
+            .filter(|&i| self.regions[i].name == name)
+            .collect();
+        matching.sort_by_key(|&i| self.regions[i].index);
+        matching
+    }
+}
+
+pub struct TextureAtlas {
+    data: TextureAtlasData,
+    regions: Vec<TextureRegion>,
+}
+
+impl TextureAtlas {
+    /// Loads an `.atlas` file and its page textures, which are looked up next to it.
+    pub fn load<P: AsRef<Path>>(graphics: &Graphics, path: P) -> Result<Self, AtlasError> {
+        let path = path.as_ref();
+        let data = TextureAtlasData::parse(&fs::read_to_string(path)?)?;
+        let pages = load_pages(graphics, path, data.pages())?;
+        Self::new(data, pages)
+    }
+
+    /// Creates the atlas from parsed data and its page textures in page order. The textures have
+    /// to be loaded upright, i.e. with `reversed` set for `Graphics::load_texture`.
+    pub fn new(data: TextureAtlasData, pages: Vec<Rc<glium::Texture2d>>) -> Result<Self, AtlasError> {
+        let mut regions = Vec::with_capacity(data.regions.len());
+        for region in &data.regions {
+            regions.push(page_region(&pages, region.page, region.x, region.y, region.packed_size())?
+                .with_rotated(region.rotated));
+        }
+
+        Ok(TextureAtlas { data, regions })
+    }
+
+    pub fn data(&self) -> &TextureAtlasData {
+        &self.data
+    }
+
+    /// The first region called `name`. Rotated regions are returned upright, see
+    /// `TextureRegion::with_rotated`.
+    pub fn find_region(&self, name: &str) -> Option<TextureRegion> {
+        self.data.regions.iter()
+            .position(|region| region.name == name)
+            .map(|i| self.regions[i].clone())
+    }
+
+    /// All regions called `name` ordered by their index, e.g. the frames of an animation.
+    pub fn find_regions(&self, name: &str) -> Vec<TextureRegion> {
+        self.data.matching(name).into_iter()
+            .map(|i| self.regions[i].clone())
+            .collect()
+    }
+
+    /// The region called `name` together with its description, e.g. to check `rotated` or to
+    /// restore stripped whitespace from `offset` and `original_size`.
+    pub fn find_region_data(&self, name: &str) -> Option<(&AtlasRegionData, &TextureRegion)> {
+        self.data.regions.iter()
+            .position(|region| region.name == name)
+            .map(|i| (&self.data.regions[i], &self.regions[i]))
+    }
+}
+
+fn parse_region_field(region: &mut AtlasRegionData, key: &str, value: &str, line: usize) -> Result<(), AtlasError> {
+    match key {
+        "rotate" => {
+            region.rotated = match value {
+                "true" | "90" => true,
+                "false" | "0" => false,
+                _ => return Err(parse_error(line, format!("unsupported rotation {:?}", value))),
+            };
+        }
+        "xy" => {
+            let values = parse_values::<u32>(value, 2, line)?;
+            let (x, y) = (values[0], values[1]);
+            region.x = x;
+            region.y = y;
+        }
+        "size" => {
+            let values = parse_values::<u32>(value, 2, line)?;
+            let (width, height) = (values[0], values[1]);
+            region.width = width;
+            region.height = height;
+        }
+        "bounds" => {
+            let values = parse_values::<u32>(value, 4, line)?;
+            let (x, y, width, height) = (values[0], values[1], values[2], values[3]);
+            region.x = x;
+            region.y = y;
+            region.width = width;
+            region.height = height;
+        }
+        "orig" => {
+            let values = parse_values::<u32>(value, 2, line)?;
+            let (width, height) = (values[0], values[1]);
+            region.original_size = (width, height);
+        }
+        "offset" => {
+            let values = parse_values::<f32>(value, 2, line)?;
+            let (x, y) = (values[0], values[1]);
+            region.offset = (x, y);
+        }
+        "offsets" => {
+            let values = parse_values::<f32>(value, 4, line)?;
+            let (x, y, width, height) = (values[0], values[1], values[2], values[3]);
+            region.offset = (x, y);
+            region.original_size = (width as u32, height as u32);
+        }
+        "index" => {
+            region.index = parse_values::<i32>(value, 1, line)?[0];
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn parse_values<T: std::str::FromStr>(value: &str, count: usize, line: usize) -> Result<Vec<T>, AtlasError> {
+    let values = value.split(',')
+        .map(|part| part.trim().parse().map_err(|_| parse_error(line, format!("invalid value {:?}", part.trim()))))
+        .collect::<Result<Vec<T>, _>>()?;
+    if values.len() != count {
+        return Err(parse_error(line, format!("expected {} values, got {:?}", count, value)));
+    }
+    Ok(values)
+}
+
+fn parse_error(line: usize, message: String) -> AtlasError {
+    AtlasError::Parse { line, message }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LEGACY_ATLAS: &str = "
+hero.png
+size: 128, 64
+format: RGBA8888
+filter: Nearest, Nearest
+repeat: none
+walk
+  rotate: false
+  xy: 2, 2
+  size: 16, 24
+  orig: 20, 24
+  offset: 2, 0
+  index: 1
+walk
+  rotate: true
+  xy: 20, 2
+  size: 16, 24
+  orig: 16, 24
+  offset: 0, 0
+  index: 0
+
+items.png
+size: 64, 64
+format: RGBA8888
+filter: Linear, Linear
+repeat: none
+sword
+  rotate: false
+  xy: 0, 0
+  size: 8, 32
+  orig: 8, 32
+  offset: 0, 0
+  index: -1
+";
+
+    #[test]
+    fn legacy_atlas_is_parsed_with_pages_and_rotation() {
+        let data = TextureAtlasData::parse(LEGACY_ATLAS).unwrap();
+        assert_eq!(data.pages(), &["hero.png".to_string(), "items.png".to_string()]);
+        assert_eq!(data.regions().len(), 3);
+
+        let first = &data.regions()[0];
+        assert_eq!((first.page, first.x, first.y, first.width, first.height), (0, 2, 2, 16, 24));
+        assert_eq!((first.original_size, first.offset), ((20, 24), (2.0, 0.0)));
+
+        let rotated = &data.regions()[1];
+        assert!(rotated.rotated);
+        assert_eq!(rotated.packed_size(), (24, 16));
+
+        let sword = &data.regions()[2];
+        assert_eq!((sword.page, sword.name.as_str(), sword.index), (1, "sword", -1));
+    }
+
+    #[test]
+    fn regions_are_ordered_by_index() {
+        let data = TextureAtlasData::parse(LEGACY_ATLAS).unwrap();
+        assert_eq!(data.matching("walk"), vec![1, 0]);
+        assert!(data.matching("missing").is_empty());
+    }
+
+    #[test]
+    fn new_format_is_parsed() {
+        let source = "page.png\nsize:64,64\nfilter:Linear,Linear\nlogo\nrotate:90\nbounds:4,8,10,20\noffsets:1,2,12,24\n";
+        let data = TextureAtlasData::parse(source).unwrap();
+        let logo = &data.regions()[0];
+        assert_eq!((logo.x, logo.y, logo.width, logo.height, logo.rotated), (4, 8, 10, 20, true));
+        assert_eq!((logo.offset, logo.original_size), ((1.0, 2.0), (12, 24)));
+    }
+
+    #[test]
+    fn invalid_rotation_is_rejected() {
+        match TextureAtlasData::parse("page.png\nlogo\n  rotate: 45\n") {
+            Err(AtlasError::Parse { line: 3, .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn rotated_region_is_drawn_upright() {
+        use glium::Surface;
+
+        use crate::graphics::render_target::RenderTarget;
+        use crate::graphics::sprite::{Sprite, SpriteDrawParams, SpriteRenderer};
+        use crate::graphics::test_support::hidden_display;
+        use crate::graphics::texture::texture_from_pixels;
+
+        let (_sdl_context, display) = hidden_display();
+
+        // A red and blue 2x1 image packed rotated counterclockwise: blue on top of red.
+        let page = texture_from_pixels(&display, &[0, 0, 255, 255, 255, 0, 0, 255], 1, 2).unwrap();
+        let data = TextureAtlasData::parse("page.png\nsize:1,2\nlogo\nrotate:90\nbounds:0,0,2,1\n").unwrap();
+        let atlas = TextureAtlas::new(data, vec![page]).unwrap();
+        let region = atlas.find_region("logo").unwrap();
+        assert!(region.is_rotated());
+        assert_eq!(region.size(), glm::vec2(2, 1));
+
+        let target = RenderTarget::new(&display, 2, 1);
+        let renderer = SpriteRenderer::new(&display, target.projection());
+        let mut surface = target.surface();
+        surface.clear_color(0.0, 0.0, 0.0, 0.0);
+        renderer.draw(&Sprite::from_texture_region(region), SpriteDrawParams::new(), &mut surface);
+
+        let pixels: Vec<Vec<(u8, u8, u8, u8)>> = target.texture().read();
+        assert_eq!(pixels[0], vec![(255, 0, 0, 255), (0, 0, 255, 255)]);
+    }
+}
@@ -0,0 +1,89 @@
+//! Shared loading for text files that place regions on separate texture pages, like `.atlas` and
+//! `.fnt` files.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::graphics::Graphics;
+use crate::graphics::texture::{RegionError, TextureLoadError, TextureRegion};
+
+#[derive(Debug)]
+pub enum PageFileError {
+    Io(io::Error),
+    Parse {
+        line: usize,
+        message: String,
+    },
+    Texture(TextureLoadError),
+    Region(RegionError),
+}
+
+impl fmt::Display for PageFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PageFileError::Io(e) => write!(f, "could not read file: {}", e),
+            PageFileError::Parse { line, message } => write!(f, "invalid file at line {}: {}", line, message),
+            PageFileError::Texture(e) => write!(f, "could not load page: {}", e),
+            PageFileError::Region(e) => write!(f, "invalid region: {}", e),
+        }
+    }
+}
+
+impl Error for PageFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PageFileError::Io(e) => Some(e),
+            PageFileError::Texture(e) => Some(e),
+            PageFileError::Region(e) => Some(e),
+            PageFileError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for PageFileError {
+    fn from(e: io::Error) -> Self {
+        PageFileError::Io(e)
+    }
+}
+
+impl From<TextureLoadError> for PageFileError {
+    fn from(e: TextureLoadError) -> Self {
+        PageFileError::Texture(e)
+    }
+}
+
+impl From<RegionError> for PageFileError {
+    fn from(e: RegionError) -> Self {
+        PageFileError::Region(e)
+    }
+}
+
+/// Loads the page textures `files` upright, looking them up next to the file at `path`.
+pub(crate) fn load_pages(graphics: &Graphics, path: &Path,
+                         files: &[String]) -> Result<Vec<Rc<glium::Texture2d>>, TextureLoadError> {
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    files.iter()
+        .map(|file| graphics.load_texture(directory.join(file), true).map(Rc::new))
+        .collect()
+}
+
+/// Looks up `page` in `pages` and takes the region with its top-left corner at (`x`, `y`) from it.
+pub(crate) fn page_region(pages: &[Rc<glium::Texture2d>], page: usize, x: u32, y: u32,
+                          size: (u32, u32)) -> Result<TextureRegion, RegionError> {
+    let texture = pages.get(page).ok_or(RegionError::MissingPage { page })?;
+    TextureRegion::from_top_left(texture.clone(), x, y, size.0, size.1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_error_names_the_line() {
+        let error = PageFileError::Parse { line: 3, message: "missing x".to_string() };
+        assert_eq!(error.to_string(), "invalid file at line 3: missing x");
+    }
+}
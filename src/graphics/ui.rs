@@ -0,0 +1,187 @@
+//! A minimal immediate-mode UI for debug panels and simple menus.
+//!
+//! Widgets are laid out in window pixels with the origin at the top-left, matching
+//! `Input::mouse_pos` and `TextRenderer::draw_queued`. The sprite batch passed to
+//! `UiContext::draw` therefore needs a matching projection, e.g.
+//! `glm::ortho(0.0, width, height, 0.0, -1.0, 1.0)`.
+
+use std::ops::Range;
+use std::rc::Rc;
+
+use glium::{DrawError, Surface};
+
+use crate::graphics::Rect;
+use crate::graphics::sprite::{Sprite, SpriteBatch};
+use crate::graphics::text::{Scale, Section, TextRenderer};
+use crate::graphics::texture::TextureRegion;
+use crate::input::{Input, MouseButton};
+
+#[derive(Clone, Copy, Debug)]
+pub struct UiStyle {
+    pub background: [f32; 4],
+    pub hovered: [f32; 4],
+    pub active: [f32; 4],
+    pub accent: [f32; 4],
+    pub text: [f32; 4],
+    pub text_size: f32,
+    pub padding: f32,
+}
+
+impl Default for UiStyle {
+    fn default() -> Self {
+        UiStyle {
+            background: [0.2, 0.2, 0.2, 0.9],
+            hovered: [0.3, 0.3, 0.3, 0.9],
+            active: [0.15, 0.15, 0.15, 0.9],
+            accent: [0.3, 0.5, 0.9, 1.0],
+            text: [1.0, 1.0, 1.0, 1.0],
+            text_size: 16.0,
+            padding: 4.0,
+        }
+    }
+}
+
+struct UiLabel {
+    text: String,
+    position: (f32, f32),
+    color: [f32; 4],
+}
+
+pub struct UiContext {
+    white: TextureRegion,
+    style: UiStyle,
+
+    mouse_pos: (f32, f32),
+    mouse_held: bool,
+    mouse_pressed: bool,
+
+    quads: Vec<(Rect, [f32; 4])>,
+    labels: Vec<UiLabel>,
+}
+
+impl UiContext {
+    pub fn new<F: glium::backend::Facade>(display: &F) -> Self {
+        let image = glium::texture::RawImage2d::from_raw_rgba(vec![255u8; 4], (1, 1));
+        let texture = glium::Texture2d::new(display, image)
+            .expect("Could not create UiContext texture.");
+
+        UiContext {
+            white: TextureRegion::new(Rc::new(texture)),
+            style: UiStyle::default(),
+
+            mouse_pos: (0.0, 0.0),
+            mouse_held: false,
+            mouse_pressed: false,
+
+            quads: Vec::new(),
+            labels: Vec::new(),
+        }
+    }
+
+    pub fn style(&self) -> &UiStyle {
+        &self.style
+    }
+
+    pub fn style_mut(&mut self) -> &mut UiStyle {
+        &mut self.style
+    }
+
+    pub fn begin(&mut self, input: &Input) {
+        let (x, y) = input.mouse_pos();
+        self.mouse_pos = (x as f32, y as f32);
+        self.mouse_held = input.is_button_held(MouseButton::Left);
+        self.mouse_pressed = input.was_button_pressed(MouseButton::Left);
+
+        self.quads.clear();
+        self.labels.clear();
+    }
+
+    pub fn label(&mut self, pos: (f32, f32), text: &str) {
+        self.labels.push(UiLabel {
+            text: text.to_owned(),
+            position: pos,
+            color: self.style.text,
+        });
+    }
+
+    pub fn button(&mut self, rect: Rect, label: &str) -> bool {
+        let hovered = self.is_hovered(rect);
+        let color = if hovered && self.mouse_held {
+            self.style.active
+        } else if hovered {
+            self.style.hovered
+        } else {
+            self.style.background
+        };
+        self.quads.push((rect, color));
+        self.label((rect.x + self.style.padding, rect.y + self.style.padding), label);
+
+        hovered && self.mouse_pressed
+    }
+
+    pub fn checkbox(&mut self, rect: Rect, label: &str, checked: &mut bool) -> bool {
+        let clicked = self.is_hovered(rect) && self.mouse_pressed;
+        if clicked {
+            *checked = !*checked;
+        }
+
+        let box_rect = Rect::new(rect.x, rect.y, rect.height, rect.height);
+        self.quads.push((box_rect, self.style.background));
+        if *checked {
+            let padding = self.style.padding;
+            let mark_rect = Rect::new(box_rect.x + padding, box_rect.y + padding,
+                                      box_rect.width - padding * 2.0, box_rect.height - padding * 2.0);
+            self.quads.push((mark_rect, self.style.accent));
+        }
+        self.label((rect.x + rect.height + self.style.padding, rect.y + self.style.padding), label);
+
+        clicked
+    }
+
+    pub fn slider(&mut self, rect: Rect, value: &mut f32, range: Range<f32>) -> bool {
+        let mut changed = false;
+        if self.is_hovered(rect) && self.mouse_held {
+            let t = ((self.mouse_pos.0 - rect.x) / rect.width).max(0.0).min(1.0);
+            let new_value = range.start + t * (range.end - range.start);
+            changed = new_value != *value;
+            *value = new_value;
+        }
+
+        let t = if range.end != range.start {
+            ((*value - range.start) / (range.end - range.start)).max(0.0).min(1.0)
+        } else {
+            0.0
+        };
+        self.quads.push((rect, self.style.background));
+        self.quads.push((Rect::new(rect.x, rect.y, rect.width * t, rect.height), self.style.accent));
+
+        changed
+    }
+
+    pub fn draw<S: Surface>(&mut self, batch: &mut SpriteBatch<S>, text: &mut TextRenderer) -> Result<(), DrawError> {
+        for &(rect, color) in &self.quads {
+            let mut sprite = Sprite::from_texture_region(&self.white);
+            sprite.set_origin(0.0, 0.0);
+            sprite.set_position(rect.x, rect.y);
+            sprite.set_scale(rect.width, rect.height);
+            sprite.set_color(color);
+            batch.draw(&sprite)?;
+        }
+
+        for label in &self.labels {
+            text.queue(Section {
+                text: &label.text,
+                screen_position: label.position,
+                scale: Scale::uniform(self.style.text_size),
+                color: label.color,
+                .. Section::default()
+            });
+        }
+
+        Ok(())
+    }
+
+    fn is_hovered(&self, rect: Rect) -> bool {
+        rect.contains(self.mouse_pos.0, self.mouse_pos.1)
+    }
+}
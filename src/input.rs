@@ -1,24 +1,204 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::hash::Hash;
 
-use sdl2;
 pub use sdl2::controller::{Axis, Button, GameController};
 pub use sdl2::keyboard::Keycode as KeyCode;
 pub use sdl2::mouse::MouseButton;
+use sdl2::mouse::MouseWheelDirection;
 
-#[derive(Clone, Copy, Debug)]
+pub const DEFAULT_AXIS_THRESHOLD: f32 = 0.5;
+pub const DEFAULT_DEADZONE: f32 = 0.15;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ElementState {
     Pressed,
     Released,
 }
 
+/// Any single input across devices, e.g. for bindings in a rebinding UI. Controller inputs refer
+/// to a player index, see `Input::player`; axes count as held once past `DEFAULT_AXIS_THRESHOLD`
+/// in the given direction. Displayed pad numbers count players from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PhysicalInput {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    ControllerButton(usize, Button),
+    ControllerAxis {
+        player: usize,
+        axis: Axis,
+        positive: bool,
+    },
+}
+
+impl fmt::Display for PhysicalInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PhysicalInput::Key(keycode) => write!(f, "{}", keycode.name()),
+            PhysicalInput::Mouse(button) => write!(f, "Mouse {:?}", button),
+            PhysicalInput::ControllerButton(player, button) => write!(f, "Pad {} {}", player + 1, button.string()),
+            PhysicalInput::ControllerAxis { player, axis, positive } =>
+                write!(f, "Pad {} {}{}", player + 1, axis.string(), if *positive { "+" } else { "-" }),
+        }
+    }
+}
+
+/// A finger on a controller touchpad, e.g. on DualShock 4 and DualSense pads. Coordinates are
+/// normalized to `0.0..=1.0` with the origin at the top-left of the touchpad.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TouchpadFinger {
+    pub touchpad: u32,
+    pub finger: u32,
+    pub x: f32,
+    pub y: f32,
+    pub pressure: f32,
+}
+
+/// A single input as applied to `Input`. Controller events carry the player index of their
+/// controller, see `Input::player`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputEvent {
+    Key(ElementState, KeyCode),
+    MouseButton(ElementState, MouseButton),
+    MouseMotion(i32, i32),
+    /// Relative mouse motion as reported by the device, also when the cursor is at the edge of the
+    /// window.
+    MouseDelta(i32, i32),
+    /// Scroll amount with positive `y` away from the user and positive `x` to the right.
+    MouseWheel(i32, i32),
+    ControllerAxis(usize, Axis, i16),
+    ControllerButton(usize, ElementState, Button),
+    ControllerTouchpad(usize, TouchpadFinger),
+    ControllerTouchpadUp(usize, TouchpadFinger),
+}
+
+/// The kind of device an input came from, see `Input::last_input_device`. Controllers are
+/// identified by player index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputDevice {
+    Keyboard,
+    Mouse,
+    Controller(usize),
+}
+
+/// The device `event` came from. Axis motion only counts once past `DEFAULT_AXIS_THRESHOLD`, so a
+/// drifting stick doesn't take over from the keyboard.
+fn event_device(event: &InputEvent) -> Option<InputDevice> {
+    match *event {
+        InputEvent::Key(..) => Some(InputDevice::Keyboard),
+        InputEvent::MouseButton(..)
+        | InputEvent::MouseMotion(..)
+        | InputEvent::MouseDelta(..)
+        | InputEvent::MouseWheel(..) => Some(InputDevice::Mouse),
+        InputEvent::ControllerAxis(player, _, value) => {
+            let moved = axis_past_threshold(value, DEFAULT_AXIS_THRESHOLD)
+                || axis_past_threshold(value, -DEFAULT_AXIS_THRESHOLD);
+            if moved { Some(InputDevice::Controller(player)) } else { None }
+        }
+        InputEvent::ControllerButton(player, ..)
+        | InputEvent::ControllerTouchpad(player, _)
+        | InputEvent::ControllerTouchpadUp(player, _) => Some(InputDevice::Controller(player)),
+    }
+}
+
+/// The input events of consecutive frames, captured with `Input::start_recording`.
+///
+/// Replays are frame exact: every recorded frame is fed back in the frame it was recorded in,
+/// relative to the start of the replay. Game state only ends up identical if the game also
+/// advances by the same delta time every frame, i.e. with a fixed timestep. Controllers are
+/// referenced by player index, so replays assume a controller in each recorded player slot.
+#[derive(Clone, Debug, Default)]
+pub struct InputRecording {
+    frames: Vec<Vec<InputEvent>>,
+}
+
+impl InputRecording {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn frames(&self) -> &[Vec<InputEvent>] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    fn begin_frame(&mut self) {
+        self.frames.push(Vec::new());
+    }
+
+    fn push(&mut self, event: InputEvent) {
+        if self.frames.is_empty() {
+            self.begin_frame();
+        }
+        self.frames.last_mut().unwrap().push(event);
+    }
+}
+
+/// Held state and per-frame edges of a set of keys or buttons. `pressed` and `released` collect
+/// every edge since the last `begin_frame`, including both edges of a tap within one frame.
+#[derive(Clone, Debug)]
+struct ButtonStates<T> {
+    held: HashSet<T>,
+    pressed: HashSet<T>,
+    released: HashSet<T>,
+}
+
+impl<T: Copy + Eq + Hash> ButtonStates<T> {
+    fn new() -> Self {
+        ButtonStates {
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+        }
+    }
+
+    fn begin_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+    }
+
+    fn press(&mut self, button: T) {
+        self.held.insert(button);
+        self.pressed.insert(button);
+    }
+
+    fn release(&mut self, button: T) {
+        self.held.remove(&button);
+        self.released.insert(button);
+    }
+
+    fn clear_held(&mut self) {
+        self.held.clear();
+    }
+
+    fn is_held(&self, button: T) -> bool {
+        self.held.contains(&button)
+    }
+
+    fn was_pressed(&self, button: T) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    fn was_released(&self, button: T) -> bool {
+        self.released.contains(&button)
+    }
+}
+
 pub struct Controller {
     instance_id: u32,
     sdl_controller: GameController,
     axis_positions: HashMap<Axis, i16>,
-    held_buttons: HashSet<Button>,
-    pressed_buttons: HashSet<Button>,
-    released_buttons: HashSet<Button>,
+    previous_axis_positions: HashMap<Axis, i16>,
+    buttons: ButtonStates<Button>,
+    touchpad_fingers: Vec<TouchpadFinger>,
+    deadzone: f32,
 }
 
 impl fmt::Debug for Controller {
@@ -26,131 +206,460 @@ impl fmt::Debug for Controller {
         f.debug_struct("Controller")
             .field("instance_id", &self.instance_id)
             .field("axis_positions", &self.axis_positions)
-            .field("held_buttons", &self.held_buttons)
-            .field("pressed_buttons", &self.pressed_buttons)
-            .field("released_buttons", &self.released_buttons)
+            .field("previous_axis_positions", &self.previous_axis_positions)
+            .field("buttons", &self.buttons)
+            .field("touchpad_fingers", &self.touchpad_fingers)
+            .field("deadzone", &self.deadzone)
             .finish()
     }
 }
 
 impl Controller {
-    fn new(instance_id: u32, sdl_controller: GameController) -> Self {
+    fn new(instance_id: u32, sdl_controller: GameController, deadzone: f32) -> Self {
         Controller {
             instance_id,
             sdl_controller,
             axis_positions: HashMap::new(),
-            held_buttons: HashSet::new(),
-            pressed_buttons: HashSet::new(),
-            released_buttons: HashSet::new(),
+            previous_axis_positions: HashMap::new(),
+            buttons: ButtonStates::new(),
+            touchpad_fingers: Vec::new(),
+            deadzone,
         }
     }
 
+    /// The raw position of `axis`, without any deadzone.
     pub fn get_axis_position(&self, axis: Axis) -> i16 {
         self.axis_positions.get(&axis).cloned().unwrap_or(0)
     }
 
+    /// The position of `axis` normalized to `-1.0..=1.0` (`0.0..=1.0` for triggers) with the
+    /// deadzone applied and the remaining range rescaled. Stick axes share a radial deadzone with
+    /// the other axis of their stick, so diagonals aren't clipped to a square.
+    pub fn axis_value(&self, axis: Axis) -> f32 {
+        let partner = match axis {
+            Axis::LeftX => Some(Axis::LeftY),
+            Axis::LeftY => Some(Axis::LeftX),
+            Axis::RightX => Some(Axis::RightY),
+            Axis::RightY => Some(Axis::RightX),
+            Axis::TriggerLeft | Axis::TriggerRight => None,
+        };
+        let value = normalize_axis(self.get_axis_position(axis));
+        let other = partner.map_or(0.0, |partner| normalize_axis(self.get_axis_position(partner)));
+        radial_deadzone(value, other, self.deadzone).0
+    }
+
+    /// Treats `axis` as a digital button that is held while the axis, normalized to `-1.0..=1.0`,
+    /// is past `threshold`. A negative threshold checks the negative direction.
+    pub fn axis_as_button(&self, axis: Axis, threshold: f32) -> bool {
+        axis_past_threshold(self.get_axis_position(axis), threshold)
+    }
+
+    /// Like `axis_as_button`, but only true in the frame the axis moved past `threshold`. It fires
+    /// again only after the axis has returned below the threshold, e.g. for stepping through menus.
+    pub fn axis_just_crossed(&self, axis: Axis, threshold: f32) -> bool {
+        let previous_position = self.previous_axis_positions.get(&axis).cloned().unwrap_or(0);
+        self.axis_as_button(axis, threshold) && !axis_past_threshold(previous_position, threshold)
+    }
+
     pub fn is_button_held(&self, button: Button) -> bool {
-        self.held_buttons.contains(&button)
+        self.buttons.is_held(button)
     }
 
     pub fn was_button_pressed(&self, button: Button) -> bool {
-        self.pressed_buttons.contains(&button)
+        self.buttons.was_pressed(button)
     }
 
     pub fn was_button_released(&self, button: Button) -> bool {
-        self.released_buttons.contains(&button)
+        self.buttons.was_released(button)
+    }
+
+    /// Whether `button` was pressed this frame, see `Input::is_key_just_pressed`.
+    pub fn is_button_just_pressed(&self, button: Button) -> bool {
+        self.buttons.was_pressed(button)
+    }
+
+    pub fn is_button_just_released(&self, button: Button) -> bool {
+        self.buttons.was_released(button)
+    }
+
+    /// Fingers currently touching the touchpad. Always empty for controllers without one.
+    pub fn touchpad_fingers(&self) -> &[TouchpadFinger] {
+        &self.touchpad_fingers
     }
 
     fn begin_frame(&mut self) {
-        self.pressed_buttons.clear();
-        self.released_buttons.clear();
+        self.buttons.begin_frame();
+        self.previous_axis_positions.clone_from(&self.axis_positions);
     }
 
-    fn press_button(&mut self, button: Button) {
-        self.held_buttons.insert(button);
-        self.pressed_buttons.insert(button);
+    fn touch(&mut self, finger: TouchpadFinger) {
+        let existing = self.touchpad_fingers.iter_mut()
+            .find(|f| f.touchpad == finger.touchpad && f.finger == finger.finger);
+        match existing {
+            Some(existing) => *existing = finger,
+            None => self.touchpad_fingers.push(finger),
+        }
+    }
+
+    fn lift(&mut self, finger: TouchpadFinger) {
+        self.touchpad_fingers.retain(|f| f.touchpad != finger.touchpad || f.finger != finger.finger);
     }
 
-    fn release_button(&mut self, button: Button) {
-        self.held_buttons.remove(&button);
-        self.released_buttons.insert(button);
+    fn release_all(&mut self) {
+        self.buttons.clear_held();
+        self.axis_positions.clear();
+        self.touchpad_fingers.clear();
     }
 }
 
-pub struct Input {
-    held_keys: HashSet<KeyCode>,
-    pressed_keys: HashSet<KeyCode>,
-    released_keys: HashSet<KeyCode>,
+/// Undoes SDL's flipped wheel direction, e.g. with natural scrolling on macOS, so scrolling up
+/// always reports a positive `y`.
+pub(crate) fn wheel_delta(x: i32, y: i32, direction: MouseWheelDirection) -> (i32, i32) {
+    match direction {
+        MouseWheelDirection::Flipped => (-x, -y),
+        _ => (x, y),
+    }
+}
+
+fn assign_player_slot(slots: &mut Vec<Option<u32>>, instance_id: u32) -> usize {
+    match slots.iter().position(Option::is_none) {
+        Some(index) => {
+            slots[index] = Some(instance_id);
+            index
+        }
+        None => {
+            slots.push(Some(instance_id));
+            slots.len() - 1
+        }
+    }
+}
+
+fn free_player_slot(slots: &mut Vec<Option<u32>>, instance_id: u32) {
+    if let Some(slot) = slots.iter_mut().find(|slot| **slot == Some(instance_id)) {
+        *slot = None;
+    }
+    while let Some(None) = slots.last() {
+        slots.pop();
+    }
+}
+
+fn normalize_axis(position: i16) -> f32 {
+    (position as f32 / i16::MAX as f32).max(-1.0)
+}
+
+/// Zeroes a stick position within `deadzone` of the center and rescales the rest so the output
+/// starts at zero right outside the deadzone and still reaches one.
+fn radial_deadzone(x: f32, y: f32, deadzone: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= deadzone {
+        return (0.0, 0.0);
+    }
+    let scale = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0) / magnitude;
+    (x * scale, y * scale)
+}
 
-    held_buttons: HashSet<MouseButton>,
-    pressed_buttons: HashSet<MouseButton>,
-    released_buttons: HashSet<MouseButton>,
+/// Keeps a deadzone within `0.0..1.0`, as `radial_deadzone` divides by `1.0 - deadzone`. NaN
+/// counts as no deadzone.
+fn clamp_deadzone(deadzone: f32) -> f32 {
+    const MAX_DEADZONE: f32 = 0.99;
+    if deadzone.is_nan() {
+        return 0.0;
+    }
+    deadzone.clamp(0.0, MAX_DEADZONE)
+}
+
+fn axis_past_threshold(position: i16, threshold: f32) -> bool {
+    let value = position as f32 / i16::MAX as f32;
+    if threshold < 0.0 {
+        value <= threshold
+    } else {
+        value >= threshold
+    }
+}
+
+pub struct Input {
+    keys: ButtonStates<KeyCode>,
+    buttons: ButtonStates<MouseButton>,
     mouse_pos: (i32, i32),
+    mouse_delta: (i32, i32),
+    mouse_delta_suppressed: bool,
+    scroll_delta: (i32, i32),
     mouse_moved: bool,
 
     controllers: Vec<Controller>,
-    controller_subsystem: sdl2::GameControllerSubsystem,
+    player_slots: Vec<Option<u32>>,
+    deadzone: f32,
+
+    recording: Option<InputRecording>,
+    replay: Option<(InputRecording, usize)>,
+
+    last_input_device: InputDevice,
 }
 
 impl Input {
-    pub(crate) fn new(sdl_context: &sdl2::Sdl) -> Self {
-        let controller_subsystem = sdl_context.game_controller().unwrap();
-
+    pub(crate) fn new() -> Self {
         Input {
-            held_keys: HashSet::new(),
-            pressed_keys: HashSet::new(),
-            released_keys: HashSet::new(),
-
-            held_buttons: HashSet::new(),
-            pressed_buttons: HashSet::new(),
-            released_buttons: HashSet::new(),
+            keys: ButtonStates::new(),
+            buttons: ButtonStates::new(),
             mouse_pos: (0, 0),
+            mouse_delta: (0, 0),
+            mouse_delta_suppressed: false,
+            scroll_delta: (0, 0),
             mouse_moved: false,
 
             controllers: Vec::new(),
-            controller_subsystem,
+            player_slots: Vec::new(),
+            deadzone: DEFAULT_DEADZONE,
+
+            recording: None,
+            replay: None,
+
+            last_input_device: InputDevice::Keyboard,
         }
     }
 
     pub fn is_key_held(&self, keycode: KeyCode) -> bool {
-        self.held_keys.contains(&keycode)
+        self.keys.is_held(keycode)
     }
 
     pub fn was_key_pressed(&self, keycode: KeyCode) -> bool {
-        self.pressed_keys.contains(&keycode)
+        self.keys.was_pressed(keycode)
     }
 
     pub fn was_key_released(&self, keycode: KeyCode) -> bool {
-        self.released_keys.contains(&keycode)
+        self.keys.was_released(keycode)
+    }
+
+    /// Whether `keycode` was pressed this frame, e.g. to step through a menu once per press. A
+    /// tap that is pressed and released within one frame still counts, so it isn't lost at low
+    /// frame rates; this is the same edge `was_key_pressed` reports.
+    pub fn is_key_just_pressed(&self, keycode: KeyCode) -> bool {
+        self.keys.was_pressed(keycode)
+    }
+
+    /// Whether `keycode` was released this frame, the counterpart of `is_key_just_pressed`.
+    pub fn is_key_just_released(&self, keycode: KeyCode) -> bool {
+        self.keys.was_released(keycode)
     }
 
     pub fn is_button_held(&self, button: MouseButton) -> bool {
-        self.held_buttons.contains(&button)
+        self.buttons.is_held(button)
     }
 
     pub fn was_button_pressed(&self, button: MouseButton) -> bool {
-        self.pressed_buttons.contains(&button)
+        self.buttons.was_pressed(button)
     }
 
     pub fn was_button_released(&self, button: MouseButton) -> bool {
-        self.released_buttons.contains(&button)
+        self.buttons.was_released(button)
+    }
+
+    pub fn is_button_just_pressed(&self, button: MouseButton) -> bool {
+        self.buttons.was_pressed(button)
+    }
+
+    pub fn is_button_just_released(&self, button: MouseButton) -> bool {
+        self.buttons.was_released(button)
+    }
+
+    /// Whether `input` is currently held, whichever device it belongs to.
+    pub fn is_pressed(&self, input: PhysicalInput) -> bool {
+        match input {
+            PhysicalInput::Key(keycode) => self.is_key_held(keycode),
+            PhysicalInput::Mouse(button) => self.is_button_held(button),
+            PhysicalInput::ControllerButton(player, button) => self.player(player)
+                .map_or(false, |controller| controller.is_button_held(button)),
+            PhysicalInput::ControllerAxis { player, axis, positive } => {
+                let threshold = if positive { DEFAULT_AXIS_THRESHOLD } else { -DEFAULT_AXIS_THRESHOLD };
+                self.axis_as_button(player, axis, threshold)
+            }
+        }
+    }
+
+    /// The device of the most recent input, e.g. to show keyboard or gamepad button prompts.
+    /// `Keyboard` until anything else is used.
+    pub fn last_input_device(&self) -> InputDevice {
+        self.last_input_device
     }
 
     pub fn mouse_pos(&self) -> (i32, i32) {
         self.mouse_pos
     }
 
+    /// How far the mouse moved since the previous frame, summed from the device's relative
+    /// motion. Zero in the frame the window regains focus, so the cursor re-entering elsewhere
+    /// doesn't make the camera jump.
+    pub fn mouse_delta(&self) -> (i32, i32) {
+        self.mouse_delta
+    }
+
+    /// Wheel scrolling since the previous frame, positive `y` for scrolling up or away from the
+    /// user regardless of the system's scroll direction setting.
+    pub fn scroll_delta(&self) -> (i32, i32) {
+        self.scroll_delta
+    }
+
     pub fn controllers(&self) -> &[Controller] {
         self.controllers.as_slice()
     }
 
+    /// The number of connected controllers. Player slots can have gaps, so this is not the range
+    /// of player indices, see `player_slot_count`.
+    pub fn controller_count(&self) -> usize {
+        self.controllers.len()
+    }
+
+    /// One past the highest player index with a controller; `0..player_slot_count()` covers every
+    /// connected player, but `player` is `None` for the free slots in between.
+    pub fn player_slot_count(&self) -> usize {
+        self.player_slots.len()
+    }
+
+    /// The controller of player `index`, counted from `0`. Each connected controller gets the
+    /// lowest free player slot and keeps it until it disconnects; the other players keep theirs,
+    /// so player 2 doesn't become player 1 when player 1's controller is unplugged. A controller
+    /// connected later fills the gap. All controller getters on `Input` take this index.
+    pub fn player(&self, index: usize) -> Option<&Controller> {
+        self.player_instance_id(index)
+            .and_then(|instance_id| self.controller(instance_id))
+    }
+
+    /// The instance id (`which`) of the controller of player `index`.
+    pub fn player_instance_id(&self, index: usize) -> Option<u32> {
+        self.player_slots.get(index).cloned().flatten()
+    }
+
+    /// The player slot of the controller with instance id `which`, see `player`.
+    pub fn player_index(&self, which: u32) -> Option<usize> {
+        self.player_slots.iter().position(|&slot| slot == Some(which))
+    }
+
+    /// Sets the light bar color of the controller of `player`, e.g. on DualShock 4 and DualSense
+    /// pads. Returns false if the player has no controller or it has no LED. Apps reach it
+    /// through `ApplicationGDX::input_mut`.
+    pub fn set_controller_led(&mut self, player: usize, r: u8, g: u8, b: u8) -> bool {
+        self.player_mut(player)
+            .map_or(false, |controller| controller.sdl_controller.set_led(r, g, b).is_ok())
+    }
+
+    /// Rumbles the controller of `player` (see `player`) for `duration_ms`, with the intensity of
+    /// the low and high frequency motors from `0` to `u16::MAX`. A new call replaces the running
+    /// effect, zero intensities stop it. Does nothing if the player has no controller or it can't
+    /// rumble.
+    pub fn rumble(&mut self, player: usize, low_freq: u16, high_freq: u16, duration_ms: u32) {
+        if let Some(controller) = self.player_mut(player) {
+            let _ = controller.sdl_controller.set_rumble(low_freq, high_freq, duration_ms);
+        }
+    }
+
+    /// Fingers on the touchpad of the controller of `player`. Empty if the player has no
+    /// controller or it has no touchpad.
+    pub fn touchpad_fingers(&self, player: usize) -> &[TouchpadFinger] {
+        self.player(player)
+            .map_or(&[][..], |controller| controller.touchpad_fingers())
+    }
+
+    /// Sets the deadzone of `axis_value` for all controllers, normalized to `0.0..1.0`; values
+    /// outside are clamped to that range. Defaults to `DEFAULT_DEADZONE`.
+    pub fn set_deadzone(&mut self, deadzone: f32) {
+        let deadzone = clamp_deadzone(deadzone);
+        self.deadzone = deadzone;
+        for controller in &mut self.controllers {
+            controller.deadzone = deadzone;
+        }
+    }
+
+    pub fn deadzone(&self) -> f32 {
+        self.deadzone
+    }
+
+    /// `Controller::axis_value` of the controller of `player`, `0.0` if the player has no
+    /// controller.
+    pub fn axis_value(&self, player: usize, axis: Axis) -> f32 {
+        self.player(player)
+            .map_or(0.0, |controller| controller.axis_value(axis))
+    }
+
+    pub fn axis_as_button(&self, player: usize, axis: Axis, threshold: f32) -> bool {
+        self.player(player)
+            .map_or(false, |controller| controller.axis_as_button(axis, threshold))
+    }
+
+    pub fn axis_just_crossed(&self, player: usize, axis: Axis, threshold: f32) -> bool {
+        self.player(player)
+            .map_or(false, |controller| controller.axis_just_crossed(axis, threshold))
+    }
+
+    /// Starts capturing the input events of every following frame, replacing any recording in
+    /// progress.
+    pub fn start_recording(&mut self) {
+        self.recording = Some(InputRecording::new());
+    }
+
+    pub fn stop_recording(&mut self) -> InputRecording {
+        self.recording.take().unwrap_or_default()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Feeds the frames of `recording` in place of live input, starting with the next frame.
+    /// Live keyboard, mouse and controller input is ignored until the replay ends, after which
+    /// live input takes over again. All keys, buttons, axes and touchpad fingers are released
+    /// before the replay starts, so it starts from the same state the recording did provided that
+    /// was started with nothing held, and again when it ends, so nothing the replay held sticks.
+    pub fn play_recording(&mut self, recording: InputRecording) {
+        self.release_all();
+        self.replay = Some((recording, 0));
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Called by the launcher once per frame before polling events. The pressed/released sets
+    /// therefore hold every edge since the previous frame and stay valid for the whole `step`,
+    /// however many sub-steps an app runs inside it.
     pub(crate) fn begin_frame(&mut self) {
-        self.pressed_keys.clear();
-        self.released_keys.clear();
+        self.clear_frame_state();
+
+        if let Some(recording) = &mut self.recording {
+            recording.begin_frame();
+        }
+
+        if let Some((recording, frame)) = self.replay.take() {
+            if let Some(events) = recording.frames.get(frame) {
+                for &event in events {
+                    self.apply_event(event);
+                }
+                self.replay = Some((recording, frame + 1));
+            } else {
+                self.release_all();
+            }
+        }
+    }
 
-        self.pressed_buttons.clear();
-        self.released_buttons.clear();
+    /// Releases everything held on every device and centers the controller axes.
+    fn release_all(&mut self) {
+        self.keys.clear_held();
+        self.buttons.clear_held();
+        for controller in &mut self.controllers {
+            controller.release_all();
+        }
+    }
+
+    /// Clears the pressed/released edges while keeping the held state. Only needed by apps that
+    /// consume edges at their own cadence, e.g. clearing after the first fixed update that saw
+    /// them through `ApplicationGDX::input_mut`; the launcher already does this at the start of
+    /// every frame.
+    pub fn clear_frame_state(&mut self) {
+        self.keys.begin_frame();
+        self.buttons.begin_frame();
         self.mouse_moved = false;
+        self.mouse_delta = (0, 0);
+        self.mouse_delta_suppressed = false;
+        self.scroll_delta = (0, 0);
 
         for controller in &mut self.controllers {
             controller.begin_frame();
@@ -159,37 +668,48 @@ impl Input {
 
     pub(crate) fn handle_keyboard_input(&mut self, state: ElementState, keycode: Option<KeyCode>) {
         if let Some(keycode) = keycode {
-            match state {
-                ElementState::Pressed => self.press_key(keycode),
-                ElementState::Released => self.release_key(keycode),
-            }
+            self.handle_event(InputEvent::Key(state, keycode));
         }
     }
 
     pub(crate) fn handle_mouse_input(&mut self, state: ElementState, button: MouseButton) {
-        match state {
-            ElementState::Pressed => self.press_button(button),
-            ElementState::Released => self.release_button(button),
-        }
+        self.handle_event(InputEvent::MouseButton(state, button));
+    }
+
+    pub(crate) fn handle_mouse_motion(&mut self, x: i32, y: i32, xrel: i32, yrel: i32) {
+        self.handle_event(InputEvent::MouseMotion(x, y));
+        self.handle_event(InputEvent::MouseDelta(xrel, yrel));
     }
 
-    pub(crate) fn handle_mouse_motion(&mut self, x: i32, y: i32) {
-        self.mouse_pos = (x, y);
+    /// Takes the wheel motion already normalized with `wheel_delta`.
+    pub(crate) fn handle_mouse_wheel(&mut self, x: i32, y: i32) {
+        self.handle_event(InputEvent::MouseWheel(x, y));
     }
 
-    pub(crate) fn handle_controller_added(&mut self, joystick_id: u32) {
-        let joystick_id = joystick_id;
-        let sdl_controller = self.controller_subsystem.open(joystick_id).unwrap();
-        self.controllers.push(Controller::new(sdl_controller.instance_id() as u32, sdl_controller));
+    pub(crate) fn handle_focus_gained(&mut self) {
+        self.mouse_delta = (0, 0);
+        self.mouse_delta_suppressed = true;
     }
 
-    pub(crate) fn handle_controller_removed(&mut self, instance_id: u32) {
+    /// Takes the controller the launcher opened for a `ControllerDeviceAdded` event and returns
+    /// its instance id.
+    pub(crate) fn handle_controller_added(&mut self, sdl_controller: GameController) -> u32 {
+        let instance_id = sdl_controller.instance_id() as u32;
+        self.controllers.push(Controller::new(instance_id, sdl_controller, self.deadzone));
+        assign_player_slot(&mut self.player_slots, instance_id);
+        instance_id
+    }
+
+    pub(crate) fn handle_controller_removed(&mut self, instance_id: u32) -> bool {
         let index = self.controllers.iter().enumerate()
             .find(|&(_, controller)| controller.instance_id == instance_id)
             .map(|(i, _)| i);
         if let Some(index) = index {
             self.controllers.remove(index);
+            free_player_slot(&mut self.player_slots, instance_id);
+            true
         } else {
+            false
         }
     }
 
@@ -197,43 +717,242 @@ impl Input {
     }
 
     pub(crate) fn handle_controller_axis(&mut self, instance_id: u32, axis: Axis, value: i16) {
-        let controller = self.controllers.iter_mut()
-            .find(|controller| controller.instance_id == instance_id);
-        if let Some(controller) = controller {
-            controller.axis_positions.insert(axis, value);
-        } else {
+        if let Some(player) = self.player_index(instance_id) {
+            self.handle_event(InputEvent::ControllerAxis(player, axis, value));
         }
     }
 
     pub(crate) fn handle_controller_button(&mut self, instance_id: u32, state: ElementState, button: Button) {
-        let controller = self.controllers.iter_mut()
-            .find(|controller| controller.instance_id == instance_id);
-        if let Some(controller) = controller {
-            match state {
-                ElementState::Pressed => controller.press_button(button),
-                ElementState::Released => controller.release_button(button),
+        if let Some(player) = self.player_index(instance_id) {
+            self.handle_event(InputEvent::ControllerButton(player, state, button));
+        }
+    }
+
+    pub(crate) fn handle_controller_touchpad(&mut self, instance_id: u32, finger: TouchpadFinger, down: bool) {
+        if let Some(player) = self.player_index(instance_id) {
+            if down {
+                self.handle_event(InputEvent::ControllerTouchpad(player, finger));
+            } else {
+                self.handle_event(InputEvent::ControllerTouchpadUp(player, finger));
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: InputEvent) {
+        if self.replay.is_some() {
+            return;
+        }
+        if let Some(recording) = &mut self.recording {
+            recording.push(event);
+        }
+        self.apply_event(event);
+    }
+
+    fn apply_event(&mut self, event: InputEvent) {
+        if let Some(device) = event_device(&event) {
+            self.last_input_device = device;
+        }
+
+        match event {
+            InputEvent::Key(ElementState::Pressed, keycode) => self.keys.press(keycode),
+            InputEvent::Key(ElementState::Released, keycode) => self.keys.release(keycode),
+            InputEvent::MouseButton(ElementState::Pressed, button) => self.buttons.press(button),
+            InputEvent::MouseButton(ElementState::Released, button) => self.buttons.release(button),
+            InputEvent::MouseMotion(x, y) => self.mouse_pos = (x, y),
+            InputEvent::MouseDelta(x, y) => {
+                if !self.mouse_delta_suppressed {
+                    self.mouse_delta = (self.mouse_delta.0 + x, self.mouse_delta.1 + y);
+                }
+            }
+            InputEvent::MouseWheel(x, y) => self.scroll_delta = (self.scroll_delta.0 + x, self.scroll_delta.1 + y),
+            InputEvent::ControllerAxis(player, axis, value) => {
+                if let Some(controller) = self.player_mut(player) {
+                    controller.axis_positions.insert(axis, value);
+                }
+            }
+            InputEvent::ControllerButton(player, state, button) => {
+                if let Some(controller) = self.player_mut(player) {
+                    match state {
+                        ElementState::Pressed => controller.buttons.press(button),
+                        ElementState::Released => controller.buttons.release(button),
+                    }
+                }
+            }
+            InputEvent::ControllerTouchpad(player, finger) => {
+                if let Some(controller) = self.player_mut(player) {
+                    controller.touch(finger);
+                }
+            }
+            InputEvent::ControllerTouchpadUp(player, finger) => {
+                if let Some(controller) = self.player_mut(player) {
+                    controller.lift(finger);
+                }
             }
-        } else {
         }
     }
 
-    fn press_key(&mut self, keycode: KeyCode) {
-        self.held_keys.insert(keycode);
-        self.pressed_keys.insert(keycode);
+    fn controller(&self, instance_id: u32) -> Option<&Controller> {
+        self.controllers.iter()
+            .find(|controller| controller.instance_id == instance_id)
+    }
+
+    fn player_mut(&mut self, index: usize) -> Option<&mut Controller> {
+        let instance_id = self.player_instance_id(index)?;
+        self.controllers.iter_mut()
+            .find(|controller| controller.instance_id == instance_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn small_axis_motion_does_not_change_the_device() {
+        assert_eq!(event_device(&InputEvent::ControllerAxis(3, Axis::LeftX, 1000)), None);
+        assert_eq!(event_device(&InputEvent::ControllerAxis(3, Axis::LeftX, -30000)),
+                   Some(InputDevice::Controller(3)));
+        assert_eq!(event_device(&InputEvent::ControllerButton(3, ElementState::Released, Button::A)),
+                   Some(InputDevice::Controller(3)));
+        assert_eq!(event_device(&InputEvent::MouseMotion(4, 2)), Some(InputDevice::Mouse));
+        assert_eq!(event_device(&InputEvent::MouseDelta(-1, 0)), Some(InputDevice::Mouse));
+        assert_eq!(event_device(&InputEvent::Key(ElementState::Pressed, KeyCode::Space)),
+                   Some(InputDevice::Keyboard));
+    }
+
+    #[test]
+    fn flipped_wheel_direction_is_normalized() {
+        assert_eq!(wheel_delta(1, 2, MouseWheelDirection::Normal), (1, 2));
+        assert_eq!(wheel_delta(1, 2, MouseWheelDirection::Flipped), (-1, -2));
+        assert_eq!(event_device(&InputEvent::MouseWheel(0, 1)), Some(InputDevice::Mouse));
     }
 
-    fn release_key(&mut self, keycode: KeyCode) {
-        self.held_keys.remove(&keycode);
-        self.released_keys.insert(keycode);
+    #[test]
+    fn scroll_delta_accumulates_and_resets_next_frame() {
+        let mut input = Input::new();
+        input.begin_frame();
+        let (x, y) = wheel_delta(0, 1, MouseWheelDirection::Flipped);
+        input.handle_mouse_wheel(x, y);
+        input.handle_mouse_wheel(1, -3);
+        assert_eq!(input.scroll_delta(), (1, -4));
+
+        input.begin_frame();
+        assert_eq!(input.scroll_delta(), (0, 0));
     }
 
-    fn press_button(&mut self, button: MouseButton) {
-        self.held_buttons.insert(button);
-        self.pressed_buttons.insert(button);
+    #[test]
+    fn just_pressed_only_in_the_first_frame_of_a_press() {
+        let mut input = Input::new();
+        input.begin_frame();
+        input.handle_keyboard_input(ElementState::Pressed, Some(KeyCode::Space));
+        assert!(input.is_key_just_pressed(KeyCode::Space));
+
+        input.begin_frame();
+        assert!(input.is_key_held(KeyCode::Space));
+        assert!(!input.is_key_just_pressed(KeyCode::Space));
+
+        input.handle_keyboard_input(ElementState::Released, Some(KeyCode::Space));
+        assert!(input.is_key_just_released(KeyCode::Space));
+        input.begin_frame();
+        assert!(!input.is_key_just_released(KeyCode::Space));
+    }
+
+    #[test]
+    fn tap_within_a_frame_is_just_pressed_and_released() {
+        let mut input = Input::new();
+        input.begin_frame();
+        input.handle_mouse_input(ElementState::Pressed, MouseButton::Left);
+        input.handle_mouse_input(ElementState::Released, MouseButton::Left);
+        assert!(!input.is_button_held(MouseButton::Left));
+        assert!(input.is_button_just_pressed(MouseButton::Left));
+        assert!(input.is_button_just_released(MouseButton::Left));
+
+        input.begin_frame();
+        assert!(!input.is_button_just_pressed(MouseButton::Left));
+    }
+
+    #[test]
+    fn rumble_without_controllers_does_nothing() {
+        let mut input = Input::new();
+        assert_eq!(input.player_instance_id(0), None);
+        input.rumble(0, u16::MAX, u16::MAX, 100);
+        input.rumble(3, 0, 0, 0);
+    }
+
+    #[test]
+    fn replay_starts_and_ends_with_nothing_held() {
+        let mut input = Input::new();
+        input.handle_keyboard_input(ElementState::Pressed, Some(KeyCode::Space));
+        input.handle_mouse_input(ElementState::Pressed, MouseButton::Left);
+
+        let recording = InputRecording {
+            frames: vec![vec![InputEvent::Key(ElementState::Pressed, KeyCode::A)], vec![]],
+        };
+        input.play_recording(recording);
+        assert!(!input.is_key_held(KeyCode::Space) && !input.is_button_held(MouseButton::Left));
+
+        input.begin_frame();
+        assert!(input.was_key_pressed(KeyCode::A));
+        input.handle_keyboard_input(ElementState::Pressed, Some(KeyCode::B));
+        assert!(!input.is_key_held(KeyCode::B));
+        input.begin_frame();
+        assert!(input.is_key_held(KeyCode::A) && input.is_replaying());
+
+        input.begin_frame();
+        assert!(!input.is_replaying());
+        assert!(!input.is_key_held(KeyCode::A));
+    }
+
+    #[test]
+    fn deadzone_is_radial_and_rescaled() {
+        assert_eq!(radial_deadzone(0.1, 0.1, 0.15), (0.0, 0.0));
+        assert_eq!(radial_deadzone(1.0, 0.0, 0.15), (1.0, 0.0));
+
+        let (x, y) = radial_deadzone(0.5, 0.0, 0.2);
+        assert!((x - 0.375).abs() < 1e-6 && y == 0.0);
+
+        // A full diagonal keeps its direction and reaches the rim instead of a square's corner.
+        let (x, y) = radial_deadzone(0.8, 0.8, 0.15);
+        assert!((x - y).abs() < 1e-6);
+        assert!(((x * x + y * y).sqrt() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn deadzone_is_clamped_instead_of_panicking() {
+        let mut input = Input::new();
+        input.set_deadzone(0.3);
+        assert_eq!(input.deadzone(), 0.3);
+        input.set_deadzone(-1.0);
+        assert_eq!(input.deadzone(), 0.0);
+        input.set_deadzone(1.0);
+        assert!(input.deadzone() < 1.0);
+        input.set_deadzone(f32::NAN);
+        assert_eq!(input.deadzone(), 0.0);
+    }
+
+    #[test]
+    fn player_slots_stay_stable_on_disconnect() {
+        let mut slots = Vec::new();
+        assert_eq!(assign_player_slot(&mut slots, 7), 0);
+        assert_eq!(assign_player_slot(&mut slots, 9), 1);
+        assert_eq!(assign_player_slot(&mut slots, 12), 2);
+
+        free_player_slot(&mut slots, 7);
+        assert_eq!(slots, vec![None, Some(9), Some(12)]);
+
+        assert_eq!(assign_player_slot(&mut slots, 15), 0);
+        free_player_slot(&mut slots, 12);
+        assert_eq!(slots, vec![Some(15), Some(9)]);
     }
 
-    fn release_button(&mut self, button: MouseButton) {
-        self.held_buttons.remove(&button);
-        self.released_buttons.insert(button);
+    #[test]
+    fn player_slot_count_covers_gaps() {
+        let mut input = Input::new();
+        input.player_slots = vec![None, Some(9)];
+        assert_eq!(input.player_slot_count(), 2);
+        assert_eq!(input.player_index(9), Some(1));
+        assert_eq!(input.player_instance_id(0), None);
+        assert_eq!(input.player_instance_id(1), Some(9));
+        assert_eq!(input.player_instance_id(2), None);
     }
 }
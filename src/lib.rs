@@ -2,8 +2,13 @@ pub use glium::{Surface, Texture2d};
 use moving_average::MovingAverage;
 
 pub use crate::app::AppGDX;
-pub use crate::config::ApplicationGDXConfig;
-pub use crate::input::{Axis, Button, Input, KeyCode, MouseButton};
+pub use crate::config::{ApplicationGDXConfig, LogicalScaling, SwapInterval, WindowKind, WindowMode};
+pub use crate::input::{
+    Axis, Button, DEFAULT_AXIS_THRESHOLD, DEFAULT_DEADZONE, ElementState, Input, InputDevice, InputEvent,
+    InputRecording, KeyCode, MouseButton, PhysicalInput, TouchpadFinger,
+};
+pub use crate::screen::{Screen, ScreenContext, ScreenManager, ScreenTransition};
+pub use crate::time::Time;
 
 use std::time::{
     Duration,
@@ -12,45 +17,138 @@ use std::time::{
 use std::thread;
 
 use crate::graphics::Graphics;
-use crate::input::ElementState;
-use crate::time::Time;
+use crate::time::FixedTimestep;
 
 mod app;
+pub mod assets;
 mod config;
 pub mod graphics;
 mod input;
+mod screen;
 mod time;
 
+const DEFAULT_FPS: u32 = 60;
+
+/// Target duration of a frame for `fps` frames per second, or zero for `0` (unlimited).
+fn frame_duration(fps: u32) -> Duration {
+    if fps == 0 {
+        return Duration::from_secs(0);
+    }
+    Duration::from_nanos(1_000_000_000 / fps as u64)
+}
+
+/// How the launcher paces frames for a frame rate cap combined with the swap interval.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FramePacing {
+    /// The loop sleeps to the frame rate cap, vsync is off.
+    Sleep,
+    /// Vsync alone paces the loop by presenting every `n`-th refresh, the loop never sleeps.
+    Vsync(u32),
+    /// The cap doesn't divide the refresh rate, so the loop sleeps to the cap and each frame is
+    /// presented at the next refresh after it. The average rate matches the cap, but frames are
+    /// shown for uneven durations.
+    SleepAndVsync,
+}
+
+/// Picks the pacing for a frame rate cap of `fps` (`0` for none). With vsync the refresh rate
+/// always wins over a higher cap. A lower cap that divides the refresh rate is reached by
+/// presenting every `n`-th refresh instead of sleeping, since sleeping and then waiting for the
+/// next refresh would drop frames below the cap.
+fn frame_pacing(fps: u32, vsync: bool, refresh_rate: Option<u32>) -> FramePacing {
+    if !vsync {
+        return FramePacing::Sleep;
+    }
+    match refresh_rate {
+        _ if fps == 0 => FramePacing::Vsync(1),
+        Some(refresh_rate) if fps >= refresh_rate => FramePacing::Vsync(1),
+        Some(refresh_rate) if refresh_rate % fps == 0 => FramePacing::Vsync(refresh_rate / fps),
+        _ => FramePacing::SleepAndVsync,
+    }
+}
+
 pub struct GDXLauncher<T: AppGDX> {
     frame_duration: Duration,
+    sleep_to_frame_duration: bool,
+    max_frame_skip: u32,
+    frame_skip_threshold: f32,
     main: ApplicationGDX,
     app: T,
 }
 
 impl<T: AppGDX> GDXLauncher<T> {
     pub fn new(config: ApplicationGDXConfig) -> Self {
-        let frame_time_ns = (1_000_000_000.0 / config.fps() as f64) as u64;
-        let frame_duration = Duration::from_nanos(frame_time_ns);
+        let mut main = ApplicationGDX::new(&config);
+
+        let refresh_rate = main.graphics.refresh_rate();
+        let fps = if config.match_refresh_rate() {
+            refresh_rate.unwrap_or(DEFAULT_FPS)
+        } else {
+            config.fps()
+        };
+        let vsync = main.graphics.swap_interval() != SwapInterval::Immediate;
+        let mut pacing = frame_pacing(fps, vsync, refresh_rate);
+        match pacing {
+            FramePacing::Vsync(frames) if frames > 1 => {
+                if let Err(e) = main.graphics.set_swap_interval_frames(frames) {
+                    eprintln!("Could not set OpenGL swap interval to {}, sleeping to the frame rate cap \
+                               instead: {}", frames, e);
+                    pacing = FramePacing::SleepAndVsync;
+                }
+            }
+            FramePacing::SleepAndVsync => {
+                let refresh_rate = match refresh_rate {
+                    Some(refresh_rate) => format!("the refresh rate of {} Hz", refresh_rate),
+                    None => "an unknown refresh rate".to_string(),
+                };
+                eprintln!("The frame rate cap of {} fps does not divide {}; frames will be presented unevenly \
+                           with vsync.", fps, refresh_rate);
+            }
+            _ => {}
+        }
+        let frame_duration = match (pacing, refresh_rate) {
+            (FramePacing::Vsync(frames), Some(refresh_rate)) if fps > 0 =>
+                frame_duration(refresh_rate) * frames,
+            _ => frame_duration(fps),
+        };
 
-        let main = ApplicationGDX::new(&config);
         let app = T::new(&main);
 
         GDXLauncher {
             frame_duration,
+            sleep_to_frame_duration: !matches!(pacing, FramePacing::Vsync(_)),
+            max_frame_skip: config.max_frame_skip(),
+            frame_skip_threshold: config.frame_skip_threshold(),
             main,
             app,
         }
     }
 
+    fn set_paused(&mut self, paused: bool) {
+        if paused == self.main.paused {
+            return;
+        }
+        self.main.paused = paused;
+        if paused {
+            self.app.pause(&self.main);
+        } else {
+            self.app.resume(&self.main);
+        }
+    }
+
     pub fn run(mut self) {
         let mut window_closed = false;
         let mut win_size = self.main.graphics.screen_size();
         let mut resized: Option<(u32, u32)> = None;
+        let mut skipped_frames = 0;
 
         while !window_closed && !self.main.should_exit() {
             let start_time = Instant::now();
-            self.main.time.update();
-            self.main.delta_times.add(self.main.time.delta_time());
+            if self.main.paused {
+                self.main.time.skip_frame();
+            } else {
+                self.main.time.update();
+                self.main.delta_times.add(self.main.time.unscaled_delta_time());
+            }
 
             self.main.input.begin_frame();
 
@@ -58,13 +156,20 @@ impl<T: AppGDX> GDXLauncher<T> {
                 use sdl2::event::Event::*;
                 use sdl2::event::WindowEvent;
                 match event {
-                    Quit { .. } => window_closed = true,
-
-                    Window { win_event, .. } => {
-                        if let WindowEvent::Resized(x, y) = win_event {
-                            resized = Some((x as u32, y as u32));
+                    Quit { .. } | AppTerminating { .. } => window_closed = true,
+                    AppWillEnterBackground { .. } => self.set_paused(true),
+                    AppDidEnterForeground { .. } => self.set_paused(false),
+
+                    Window { win_event, .. } => match win_event {
+                        WindowEvent::Resized(x, y) => resized = Some((x as u32, y as u32)),
+                        WindowEvent::FocusLost | WindowEvent::Minimized => self.set_paused(true),
+                        WindowEvent::FocusGained => {
+                            self.main.input.handle_focus_gained();
+                            self.set_paused(false);
                         }
-                    }
+                        WindowEvent::Restored => self.set_paused(false),
+                        _ => {}
+                    },
 
                     KeyDown { keycode, repeat, .. } => {
                         if !repeat {
@@ -78,13 +183,25 @@ impl<T: AppGDX> GDXLauncher<T> {
                         self.main.input.handle_mouse_input(ElementState::Pressed, mouse_btn),
                     MouseButtonUp { mouse_btn, .. } =>
                         self.main.input.handle_mouse_input(ElementState::Released, mouse_btn),
-                    MouseMotion { x, y, .. } =>
-                        self.main.input.handle_mouse_motion(x, y),
+                    MouseMotion { x, y, xrel, yrel, .. } =>
+                        self.main.input.handle_mouse_motion(x, y, xrel, yrel),
+                    MouseWheel { x, y, direction, .. } => {
+                        let (x, y) = input::wheel_delta(x, y, direction);
+                        self.main.input.handle_mouse_wheel(x, y);
+                    }
 
-                    ControllerDeviceAdded { which, .. } =>
-                        self.main.input.handle_controller_added(which),
-                    ControllerDeviceRemoved { which, .. } =>
-                        self.main.input.handle_controller_removed(which),
+                    ControllerDeviceAdded { which, .. } => {
+                        let sdl_controller = self.main.controller_subsystem.open(which).unwrap();
+                        let instance_id = self.main.input.handle_controller_added(sdl_controller);
+                        let player = self.main.input.player_index(instance_id);
+                        self.app.on_controller_connected(instance_id, player, &self.main);
+                    }
+                    ControllerDeviceRemoved { which, .. } => {
+                        let player = self.main.input.player_index(which);
+                        if self.main.input.handle_controller_removed(which) {
+                            self.app.on_controller_disconnected(which, player, &self.main);
+                        }
+                    }
                     ControllerDeviceRemapped { which, .. } =>
                         self.main.input.handle_controller_remapped(which),
                     ControllerAxisMotion { which, axis, value, .. } =>
@@ -93,6 +210,15 @@ impl<T: AppGDX> GDXLauncher<T> {
                         self.main.input.handle_controller_button(which, ElementState::Pressed, button),
                     ControllerButtonUp { which, button, .. } =>
                         self.main.input.handle_controller_button(which, ElementState::Released, button),
+                    ControllerTouchpadDown { which, touchpad, finger, x, y, pressure, .. } |
+                    ControllerTouchpadMotion { which, touchpad, finger, x, y, pressure, .. } => {
+                        let finger = TouchpadFinger { touchpad, finger, x, y, pressure };
+                        self.main.input.handle_controller_touchpad(which, finger, true);
+                    }
+                    ControllerTouchpadUp { which, touchpad, finger, x, y, pressure, .. } => {
+                        let finger = TouchpadFinger { touchpad, finger, x, y, pressure };
+                        self.main.input.handle_controller_touchpad(which, finger, false);
+                    }
 
                     _ => {}
                 }
@@ -104,49 +230,95 @@ impl<T: AppGDX> GDXLauncher<T> {
                 win_size = cur_win_size;
             }
             if let Some(size) = resized {
+                self.main.graphics.resize();
                 self.app.resize(size, &self.main);
                 resized = None;
             }
 
+            if self.main.paused {
+                thread::sleep(frame_duration(DEFAULT_FPS));
+                continue;
+            }
+
+            let fixed_steps = match &mut self.main.fixed_timestep {
+                Some(fixed_timestep) => fixed_timestep.advance(self.main.time.delta_time()),
+                None => 0,
+            };
+            for _ in 0..fixed_steps {
+                self.app.fixed_step(&mut self.main);
+            }
+
             self.app.step(&mut self.main);
 
             let time_elapsed = start_time.elapsed();
             self.main.frame_times.add(Time::duration_as_f64(time_elapsed));
-            if time_elapsed < self.frame_duration {
+            if self.frame_duration > Duration::from_secs(0) {
+                let load = self.main.frame_time() / Time::duration_as_f64(self.frame_duration);
+                self.app.on_performance_sample(load as f32, &self.main);
+            }
+            let overrun_limit = self.frame_duration.mul_f32(1.0 + self.frame_skip_threshold.max(0.0));
+            let overran = self.frame_duration > Duration::from_secs(0) && time_elapsed > overrun_limit;
+            if overran && skipped_frames < self.max_frame_skip {
+                skipped_frames += 1;
+                self.main.skip_render = true;
+            } else {
+                skipped_frames = 0;
+                self.main.skip_render = false;
+            }
+
+            if self.sleep_to_frame_duration && time_elapsed < self.frame_duration {
                 thread::sleep(self.frame_duration - time_elapsed);
             }
         }
 
-        self.app.destroy(&self.main);
+        self.app.destroy(&mut self.main);
+
+        let GDXLauncher { main, app, .. } = self;
+        drop(app);
+        drop(main);
     }
 }
 
 pub struct ApplicationGDX {
     sdl_context: sdl2::Sdl,
+    controller_subsystem: sdl2::GameControllerSubsystem,
     time: Time,
+    fixed_timestep: Option<FixedTimestep>,
     graphics: Graphics,
     input: Input,
 
     frame_times: MovingAverage<f64>,
     delta_times: MovingAverage<f64>,
     should_exit: bool,
+    skip_render: bool,
+    paused: bool,
 }
 
 impl ApplicationGDX {
     fn new(config: &ApplicationGDXConfig) -> Self {
+        for (key, value) in config.sdl_hints() {
+            if !sdl2::hint::set(key, value) {
+                eprintln!("Could not set SDL hint {} to {}", key, value);
+            }
+        }
+
         let sdl_context = sdl2::init().unwrap();
         let graphics = Graphics::new(config, &sdl_context);
-        let input = Input::new(&sdl_context);
+        let controller_subsystem = sdl_context.game_controller().unwrap();
 
         Self {
             sdl_context,
-            time: Time::new(),
+            controller_subsystem,
+            time: Time::with_smoothing(config.delta_smoothing()),
+            fixed_timestep: Some(config.update_rate()).filter(|&rate| rate > 0).map(FixedTimestep::new),
             graphics,
-            input,
+            input: Input::new(),
 
             frame_times: MovingAverage::new(200),
             delta_times: MovingAverage::new(200),
             should_exit: false,
+            skip_render: false,
+            paused: false,
         }
     }
 
@@ -154,6 +326,32 @@ impl ApplicationGDX {
         &self.time
     }
 
+    pub fn time_mut(&mut self) -> &mut Time {
+        &mut self.time
+    }
+
+    /// Seconds of game time since the last frame, scaled by `Time::set_time_scale`.
+    pub fn delta_time(&self) -> f32 {
+        self.time.delta_time() as f32
+    }
+
+    /// Seconds since launch, not counting the time the app was paused, see `AppGDX::pause`.
+    pub fn elapsed_time(&self) -> f64 {
+        self.time.elapsed_time()
+    }
+
+    /// Duration of a fixed step in seconds, or `None` without an update rate.
+    pub fn fixed_delta_time(&self) -> Option<f64> {
+        self.fixed_timestep.as_ref().map(FixedTimestep::step)
+    }
+
+    /// How far rendering is between the last and the next fixed step, from `0.0` to `1.0`. Render
+    /// the state interpolated as `previous + (current - previous) * alpha` to keep motion smooth
+    /// when the render rate doesn't match the update rate. Always `0.0` without an update rate.
+    pub fn interpolation_alpha(&self) -> f32 {
+        self.fixed_timestep.as_ref().map_or(0.0, |fixed_timestep| fixed_timestep.alpha() as f32)
+    }
+
     pub fn graphics(&self) -> &Graphics {
         &self.graphics
     }
@@ -166,6 +364,12 @@ impl ApplicationGDX {
         &self.input
     }
 
+    /// Mutable access to the input, e.g. for recording and replaying it or for controller
+    /// settings like rumble and the deadzone.
+    pub fn input_mut(&mut self) -> &mut Input {
+        &mut self.input
+    }
+
     pub fn frame_time(&self) -> f64 {
         self.frame_times.average()
     }
@@ -182,6 +386,18 @@ impl ApplicationGDX {
         self.should_exit
     }
 
+    /// True between `AppGDX::pause` and `AppGDX::resume`. The launcher doesn't call `step` or
+    /// `fixed_step` while paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// False for frames in which the launcher skips rendering to catch up, see
+    /// `ApplicationGDXConfig::with_max_frame_skip`. `step` should still update the game logic.
+    pub fn should_render(&self) -> bool {
+        !self.skip_render
+    }
+
     fn event_pump(&self) -> sdl2::EventPump {
         self.sdl_context.event_pump()
             .unwrap()
@@ -190,7 +406,58 @@ impl ApplicationGDX {
 
 #[cfg(test)]
 mod test {
+    use super::*;
+
     #[test]
     fn it_works() {
     }
+
+    #[test]
+    fn zero_fps_means_unlimited() {
+        assert_eq!(frame_duration(0), Duration::from_secs(0));
+        assert_eq!(frame_duration(60), Duration::from_nanos(16_666_666));
+        assert_eq!(frame_duration(360), Duration::from_nanos(2_777_777));
+    }
+
+    #[test]
+    fn frame_pacing_for_each_vsync_combination() {
+        // Without vsync the cap is reached by sleeping, `0` never sleeps.
+        assert_eq!(frame_pacing(0, false, Some(60)), FramePacing::Sleep);
+        assert_eq!(frame_pacing(30, false, Some(60)), FramePacing::Sleep);
+        // Uncapped or capped above the refresh rate runs at the refresh rate.
+        assert_eq!(frame_pacing(0, true, Some(60)), FramePacing::Vsync(1));
+        assert_eq!(frame_pacing(60, true, Some(60)), FramePacing::Vsync(1));
+        assert_eq!(frame_pacing(144, true, Some(60)), FramePacing::Vsync(1));
+        // A cap dividing the refresh rate presents every n-th refresh: 30 fps on 60 Hz, 48 on 144.
+        assert_eq!(frame_pacing(30, true, Some(60)), FramePacing::Vsync(2));
+        assert_eq!(frame_pacing(48, true, Some(144)), FramePacing::Vsync(3));
+        // Anything else averages out at the cap.
+        assert_eq!(frame_pacing(50, true, Some(60)), FramePacing::SleepAndVsync);
+        assert_eq!(frame_pacing(30, true, None), FramePacing::SleepAndVsync);
+    }
+
+    // Opens a hidden window, see `graphics::test_support` for running it.
+    #[test]
+    #[ignore]
+    fn input_is_recorded_and_replayed_through_input_mut() {
+        let mut gdx = ApplicationGDX::new(&ApplicationGDXConfig::new().with_start_hidden(true));
+
+        gdx.input_mut().start_recording();
+        gdx.input_mut().begin_frame();
+        gdx.input_mut().handle_keyboard_input(ElementState::Pressed, Some(KeyCode::Space));
+        gdx.input_mut().begin_frame();
+        gdx.input_mut().handle_keyboard_input(ElementState::Released, Some(KeyCode::Space));
+        let recording = gdx.input_mut().stop_recording();
+        assert_eq!(recording.len(), 2);
+        assert!(!gdx.input().is_key_held(KeyCode::Space));
+
+        gdx.input_mut().play_recording(recording);
+        assert!(gdx.input().is_replaying());
+        gdx.input_mut().begin_frame();
+        assert!(gdx.input().was_key_pressed(KeyCode::Space) && gdx.input().is_key_held(KeyCode::Space));
+        gdx.input_mut().begin_frame();
+        assert!(gdx.input().was_key_released(KeyCode::Space));
+        gdx.input_mut().begin_frame();
+        assert!(!gdx.input().is_replaying());
+    }
 }
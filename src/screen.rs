@@ -0,0 +1,314 @@
+use crate::ApplicationGDX;
+
+/// What screens get passed to their hooks. Implemented by `ApplicationGDX`, which is the default
+/// everywhere; other implementations are mostly useful to test screens without a window.
+pub trait ScreenContext {
+    /// Size newly pushed screens are resized to.
+    fn screen_size(&self) -> (u32, u32);
+}
+
+impl ScreenContext for ApplicationGDX {
+    fn screen_size(&self) -> (u32, u32) {
+        self.graphics().screen_size()
+    }
+}
+
+/// What the `ScreenManager` should do after a screen rendered a frame.
+pub enum ScreenTransition<G = ApplicationGDX> {
+    None,
+    /// Covers the current screen with a new one, the current screen is paused until the new one is
+    /// popped.
+    Push(Box<dyn Screen<G>>),
+    /// Removes the current screen and resumes the one below.
+    Pop,
+    /// Replaces the whole stack with a new screen.
+    Set(Box<dyn Screen<G>>),
+}
+
+/// A part of the game like the main menu or the gameplay, managed by a `ScreenManager`. The hooks
+/// mirror the ones of `AppGDX`.
+pub trait Screen<G = ApplicationGDX> {
+    /// Called when the screen becomes part of the stack.
+    #[allow(unused_variables)]
+    fn show(&mut self, gdx: &G) {}
+
+    /// Called when the screen is removed from the stack, right before `dispose`.
+    #[allow(unused_variables)]
+    fn hide(&mut self, gdx: &G) {}
+
+    /// Called once when the screen is removed from the stack or the manager is disposed, to
+    /// release textures and other resources while the GL context is still alive. The screen is
+    /// dropped right after.
+    #[allow(unused_variables)]
+    fn dispose(&mut self, gdx: &G) {}
+
+    /// Called once per frame while the screen is on top of the stack.
+    fn render(&mut self, gdx: &mut G) -> ScreenTransition<G>;
+
+    #[allow(unused_variables)]
+    fn resize(&mut self, size: (u32, u32), gdx: &G) {}
+
+    /// Called when another screen is pushed on top of this one or the app is paused.
+    #[allow(unused_variables)]
+    fn pause(&mut self, gdx: &G) {}
+
+    /// Called when this screen is on top of the stack again or the app is resumed.
+    #[allow(unused_variables)]
+    fn resume(&mut self, gdx: &G) {}
+}
+
+/// A stack of screens, libGDX's `Game`. An `AppGDX` owns one and forwards its `step`, `resize`,
+/// `pause` and `resume` calls to it, only the top screen is rendered.
+pub struct ScreenManager<G = ApplicationGDX> {
+    screens: Vec<Box<dyn Screen<G>>>,
+}
+
+impl<G> Default for ScreenManager<G> {
+    fn default() -> Self {
+        ScreenManager { screens: Vec::new() }
+    }
+}
+
+impl<G: ScreenContext> ScreenManager<G> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shows `screen` on top of the stack and pauses the screen below.
+    pub fn push(&mut self, mut screen: Box<dyn Screen<G>>, gdx: &G) {
+        if let Some(top) = self.screens.last_mut() {
+            top.pause(gdx);
+        }
+        screen.show(gdx);
+        screen.resize(gdx.screen_size(), gdx);
+        self.screens.push(screen);
+    }
+
+    /// Hides, disposes and removes the top screen and resumes the one below.
+    pub fn pop(&mut self, gdx: &G) {
+        if let Some(mut screen) = self.screens.pop() {
+            screen.hide(gdx);
+            screen.dispose(gdx);
+        }
+        if let Some(top) = self.screens.last_mut() {
+            top.resume(gdx);
+        }
+    }
+
+    /// Hides, disposes and removes all screens, top first, and shows `screen`.
+    pub fn set(&mut self, screen: Box<dyn Screen<G>>, gdx: &G) {
+        self.dispose(gdx);
+        self.push(screen, gdx);
+    }
+
+    /// Hides, disposes and removes all screens, top first. Call this from `AppGDX::destroy`.
+    pub fn dispose(&mut self, gdx: &G) {
+        while let Some(mut screen) = self.screens.pop() {
+            screen.hide(gdx);
+            screen.dispose(gdx);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.screens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.screens.is_empty()
+    }
+
+    /// Renders the top screen and applies the transition it returned.
+    pub fn step(&mut self, gdx: &mut G) {
+        let transition = match self.screens.last_mut() {
+            Some(top) => top.render(gdx),
+            None => return,
+        };
+
+        match transition {
+            ScreenTransition::None => {}
+            ScreenTransition::Push(screen) => self.push(screen, gdx),
+            ScreenTransition::Pop => self.pop(gdx),
+            ScreenTransition::Set(screen) => self.set(screen, gdx),
+        }
+    }
+
+    /// Resizes all screens, so covered screens are up to date once they are on top again.
+    pub fn resize(&mut self, size: (u32, u32), gdx: &G) {
+        for screen in &mut self.screens {
+            screen.resize(size, gdx);
+        }
+    }
+
+    pub fn pause(&mut self, gdx: &G) {
+        if let Some(top) = self.screens.last_mut() {
+            top.pause(gdx);
+        }
+    }
+
+    pub fn resume(&mut self, gdx: &G) {
+        if let Some(top) = self.screens.last_mut() {
+            top.resume(gdx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    struct Context;
+
+    impl ScreenContext for Context {
+        fn screen_size(&self) -> (u32, u32) {
+            (800, 600)
+        }
+    }
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// Records every hook call as "<name> <hook>" and returns `transition` from its first render.
+    struct Recorder {
+        name: &'static str,
+        log: Log,
+        transition: Option<ScreenTransition<Context>>,
+    }
+
+    impl Recorder {
+        fn boxed(name: &'static str, log: &Log) -> Box<dyn Screen<Context>> {
+            Self::with_transition(name, log, ScreenTransition::None)
+        }
+
+        fn with_transition(name: &'static str, log: &Log,
+                           transition: ScreenTransition<Context>) -> Box<dyn Screen<Context>> {
+            Box::new(Recorder { name, log: log.clone(), transition: Some(transition) })
+        }
+
+        fn record(&self, hook: &str) {
+            self.log.borrow_mut().push(format!("{} {}", self.name, hook));
+        }
+    }
+
+    impl Screen<Context> for Recorder {
+        fn show(&mut self, _: &Context) {
+            self.record("show");
+        }
+
+        fn hide(&mut self, _: &Context) {
+            self.record("hide");
+        }
+
+        fn dispose(&mut self, _: &Context) {
+            self.record("dispose");
+        }
+
+        fn render(&mut self, _: &mut Context) -> ScreenTransition<Context> {
+            self.record("render");
+            self.transition.take().unwrap_or(ScreenTransition::None)
+        }
+
+        fn resize(&mut self, size: (u32, u32), _: &Context) {
+            self.record(&format!("resize {}x{}", size.0, size.1));
+        }
+
+        fn pause(&mut self, _: &Context) {
+            self.record("pause");
+        }
+
+        fn resume(&mut self, _: &Context) {
+            self.record("resume");
+        }
+    }
+
+    fn take(log: &Log) -> Vec<String> {
+        log.borrow_mut().drain(..).collect()
+    }
+
+    #[test]
+    fn push_shows_the_new_screen_and_pauses_the_one_below() {
+        let log = Log::default();
+        let mut screens = ScreenManager::new();
+        screens.push(Recorder::boxed("menu", &log), &Context);
+        assert_eq!(take(&log), ["menu show", "menu resize 800x600"]);
+
+        screens.push(Recorder::boxed("game", &log), &Context);
+        assert_eq!(take(&log), ["menu pause", "game show", "game resize 800x600"]);
+        assert_eq!(screens.len(), 2);
+    }
+
+    #[test]
+    fn pop_disposes_the_top_screen_and_resumes_the_one_below() {
+        let log = Log::default();
+        let mut screens = ScreenManager::new();
+        screens.push(Recorder::boxed("menu", &log), &Context);
+        screens.push(Recorder::boxed("game", &log), &Context);
+        take(&log);
+
+        screens.pop(&Context);
+        assert_eq!(take(&log), ["game hide", "game dispose", "menu resume"]);
+        assert_eq!(screens.len(), 1);
+    }
+
+    #[test]
+    fn set_disposes_every_screen_top_first_before_showing_the_new_one() {
+        let log = Log::default();
+        let mut screens = ScreenManager::new();
+        screens.push(Recorder::boxed("menu", &log), &Context);
+        screens.push(Recorder::boxed("options", &log), &Context);
+        take(&log);
+
+        screens.set(Recorder::boxed("game", &log), &Context);
+        assert_eq!(take(&log), [
+            "options hide", "options dispose", "menu hide", "menu dispose", "game show", "game resize 800x600",
+        ]);
+        assert_eq!(screens.len(), 1);
+    }
+
+    #[test]
+    fn dispose_hides_and_disposes_every_screen_top_first() {
+        let log = Log::default();
+        let mut screens = ScreenManager::new();
+        screens.push(Recorder::boxed("menu", &log), &Context);
+        screens.push(Recorder::boxed("game", &log), &Context);
+        take(&log);
+
+        screens.dispose(&Context);
+        assert_eq!(take(&log), ["game hide", "game dispose", "menu hide", "menu dispose"]);
+        assert!(screens.is_empty());
+    }
+
+    #[test]
+    fn step_renders_the_top_screen_and_applies_its_transition() {
+        let log = Log::default();
+        let mut screens = ScreenManager::new();
+        let pause_menu = Recorder::with_transition("pause", &log, ScreenTransition::Pop);
+        screens.push(Recorder::with_transition("game", &log, ScreenTransition::Push(pause_menu)), &Context);
+        take(&log);
+
+        screens.step(&mut Context);
+        assert_eq!(take(&log), ["game render", "game pause", "pause show", "pause resize 800x600"]);
+
+        screens.step(&mut Context);
+        assert_eq!(take(&log), ["pause render", "pause hide", "pause dispose", "game resume"]);
+
+        screens.step(&mut Context);
+        assert_eq!(take(&log), ["game render"]);
+        assert_eq!(screens.len(), 1);
+    }
+
+    #[test]
+    fn only_resize_reaches_covered_screens() {
+        let log = Log::default();
+        let mut screens = ScreenManager::new();
+        screens.push(Recorder::boxed("menu", &log), &Context);
+        screens.push(Recorder::boxed("game", &log), &Context);
+        take(&log);
+
+        screens.resize((1024, 768), &Context);
+        screens.pause(&Context);
+        screens.resume(&Context);
+        assert_eq!(take(&log), ["menu resize 1024x768", "game resize 1024x768", "game pause", "game resume"]);
+    }
+}
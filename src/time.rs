@@ -3,10 +3,14 @@ use std::time::{
     Instant,
 };
 
+use moving_average::MovingAverage;
 
 pub struct Time {
     delta_time: Duration,
     last_frame_time: Instant,
+    elapsed_time: f64,
+    time_scale: f32,
+    smoothed_delta_times: Option<MovingAverage<f64>>,
 }
 
 impl Time {
@@ -14,20 +18,175 @@ impl Time {
         Self {
             delta_time: Duration::from_secs(0),
             last_frame_time: Instant::now(),
+            elapsed_time: 0.0,
+            time_scale: 1.0,
+            smoothed_delta_times: None,
+        }
+    }
+
+    /// Averages the last `frames` deltas for `smoothed_delta_time`; `0` disables smoothing.
+    pub fn with_smoothing(frames: usize) -> Self {
+        Self {
+            smoothed_delta_times: if frames > 0 { Some(MovingAverage::new(frames)) } else { None },
+            .. Self::new()
         }
     }
 
     pub fn update(&mut self) {
         let frame_time = Instant::now();
-        self.delta_time = frame_time - self.last_frame_time;
+        let delta_time = frame_time - self.last_frame_time;
         self.last_frame_time = frame_time;
+        self.advance(delta_time);
+    }
+
+    /// Starts a new frame that took `delta_time` of real time.
+    fn advance(&mut self, delta_time: Duration) {
+        self.delta_time = delta_time;
+        self.elapsed_time += self.unscaled_delta_time();
+        if let Some(smoothed_delta_times) = &mut self.smoothed_delta_times {
+            smoothed_delta_times.add(Self::duration_as_f64(self.delta_time));
+        }
     }
 
+    /// Starts a new frame without letting time pass, used while the app is paused.
+    pub(crate) fn skip_frame(&mut self) {
+        self.delta_time = Duration::from_secs(0);
+        self.last_frame_time = Instant::now();
+    }
+
+    /// Delta time of the last frame in game time, i.e. multiplied by the time scale.
     pub fn delta_time(&self) -> f64 {
+        self.unscaled_delta_time() * self.time_scale as f64
+    }
+
+    /// Multiplies `delta_time` and `smoothed_delta_time`, e.g. `0.25` for slow motion or `0.0` to
+    /// freeze game time while the loop keeps running. Frame pacing and `elapsed_time` follow real
+    /// time. Defaults to `1.0`.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        assert!(time_scale >= 0.0, "The time scale must not be negative!");
+        self.time_scale = time_scale;
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Seconds elapsed since launch, not counting the time the app was paused.
+    pub fn elapsed_time(&self) -> f64 {
+        self.elapsed_time
+    }
+
+    /// Real delta time of the last frame that is never affected by time scaling.
+    pub fn unscaled_delta_time(&self) -> f64 {
         Self::duration_as_f64(self.delta_time)
     }
 
+    /// Same as `unscaled_delta_time`.
+    pub fn raw_delta_time(&self) -> f64 {
+        self.unscaled_delta_time()
+    }
+
+    /// Average delta time of the last few frames if smoothing is enabled, otherwise the same as
+    /// `delta_time`. Evens out micro-stutter in smooth scrolling and camera motion, but lags
+    /// behind real frame time changes, so keep physics and other simulation on `delta_time`.
+    pub fn smoothed_delta_time(&self) -> f64 {
+        match &self.smoothed_delta_times {
+            Some(smoothed_delta_times) => smoothed_delta_times.average() * self.time_scale as f64,
+            None => self.delta_time(),
+        }
+    }
+
+    pub fn now() -> Instant {
+        Instant::now()
+    }
+
+    /// Seconds elapsed between `instant` and now, for clocks that tick at their own cadence
+    /// independently of the frame delta.
+    pub fn delta_since(&self, instant: Instant) -> f64 {
+        Self::duration_as_f64(Instant::now().saturating_duration_since(instant))
+    }
+
     pub fn duration_as_f64(duration: Duration) -> f64 {
         duration.as_secs() as f64 + (duration.subsec_nanos() as f64 / 1_000_000_000.0)
     }
 }
+
+/// Most fixed steps run in a single frame. Frame time beyond that is dropped, so after a hang the
+/// simulation falls behind real time instead of spending ever longer frames catching up.
+pub(crate) const MAX_FIXED_STEPS_PER_FRAME: u32 = 5;
+
+/// Accumulates frame time and splits it into steps of a fixed duration.
+pub(crate) struct FixedTimestep {
+    step: f64,
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    pub fn new(rate: u32) -> Self {
+        assert!(rate > 0, "The fixed update rate must be positive!");
+        Self {
+            step: 1.0 / rate as f64,
+            accumulator: 0.0,
+        }
+    }
+
+    /// Adds `delta_time` and returns how many fixed steps to run now. The accumulator is clamped
+    /// to `MAX_FIXED_STEPS_PER_FRAME` steps.
+    pub fn advance(&mut self, delta_time: f64) -> u32 {
+        let max_accumulated = self.step * MAX_FIXED_STEPS_PER_FRAME as f64;
+        self.accumulator = (self.accumulator + delta_time.max(0.0)).min(max_accumulated);
+
+        let steps = (self.accumulator / self.step) as u32;
+        self.accumulator -= steps as f64 * self.step;
+        steps
+    }
+
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// How far the leftover time is into the next step, from `0.0` to `1.0`.
+    pub fn alpha(&self) -> f64 {
+        self.accumulator / self.step
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn zero_time_scale_freezes_only_game_time() {
+        let mut time = Time::with_smoothing(2);
+        time.set_time_scale(0.0);
+        time.advance(Duration::from_millis(250));
+        time.advance(Duration::from_millis(250));
+
+        assert_eq!(time.delta_time(), 0.0);
+        assert_eq!(time.smoothed_delta_time(), 0.0);
+        assert_eq!(time.unscaled_delta_time(), 0.25);
+        assert_eq!(time.raw_delta_time(), 0.25);
+        assert_eq!(time.elapsed_time(), 0.5);
+
+        time.set_time_scale(0.5);
+        time.advance(Duration::from_millis(500));
+        assert_eq!(time.delta_time(), 0.25);
+        assert_eq!(time.elapsed_time(), 1.0);
+    }
+
+    #[test]
+    fn fixed_timestep_runs_whole_steps_and_keeps_the_rest() {
+        let mut timestep = FixedTimestep::new(4);
+        assert_eq!(timestep.advance(0.125), 0);
+        assert_eq!(timestep.alpha(), 0.5);
+        assert_eq!(timestep.advance(0.75), 3);
+        assert_eq!(timestep.alpha(), 0.5);
+    }
+
+    #[test]
+    fn fixed_timestep_clamps_after_a_hang() {
+        let mut timestep = FixedTimestep::new(4);
+        assert_eq!(timestep.advance(3.0), MAX_FIXED_STEPS_PER_FRAME);
+        assert_eq!(timestep.advance(0.0), 0);
+    }
+}